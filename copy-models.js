@@ -1,3 +1,4 @@
+const crypto = require('crypto');
 const fs = require('fs');
 const path = require('path');
 
@@ -5,18 +6,58 @@ const path = require('path');
 const sourceDir = path.join(__dirname, 'src-tauri', 'scripts', 'assets', 'model');
 const destDir = path.join(__dirname, 'src-tauri', 'assets', 'model');
 
+const modelFile = 'mobilenet_v2.onnx';
+// Known-good digest of the upstream model, committed next to the labels in
+// `sha256sum` format. The app verifies the bundled model against the copy.
+const checksumFile = `${modelFile}.sha256`;
+
 // Create destination directory if it doesn't exist
 if (!fs.existsSync(destDir)) {
   fs.mkdirSync(destDir, { recursive: true });
   console.log(`Created directory: ${destDir}`);
 }
 
-// Copy model files
-const modelFiles = ['mobilenet_v2.onnx', 'labels.txt'];
+function fail(message) {
+  console.error(message);
+  process.exit(1);
+}
+
+// Refuse to bundle a model that is not the one the digest was recorded for,
+// e.g. an HTML error page or Git LFS pointer saved under the model's name
+function verifyModel(modelPath) {
+  const checksumPath = path.join(sourceDir, checksumFile);
+  if (!fs.existsSync(checksumPath)) {
+    fail(`No known-good checksum at ${checksumPath}, refusing to bundle an unverified model`);
+  }
+
+  const expected = fs.readFileSync(checksumPath, 'utf8').trim().split(/\s+/)[0].toLowerCase();
+  if (!/^[0-9a-f]{64}$/.test(expected)) {
+    fail(`${checksumPath} does not hold a SHA-256 digest`);
+  }
+
+  const model = fs.readFileSync(modelPath);
+  const actual = crypto.createHash('sha256').update(model).digest('hex');
+  if (actual !== expected) {
+    fail(
+      `${modelPath} (${model.length} bytes) does not match the known-good checksum:\n` +
+        `  expected ${expected}\n  actual   ${actual}\n` +
+        'Download the model again with src-tauri/scripts/setup_models.bat.'
+    );
+  }
+  console.log(`Verified ${modelFile} against ${checksumPath}`);
+}
+
+const modelSource = path.join(sourceDir, modelFile);
+if (fs.existsSync(modelSource)) {
+  verifyModel(modelSource);
+}
+
+// Copy model files, with the committed checksum as the bundled sidecar
+const modelFiles = [modelFile, checksumFile, 'labels.txt'];
 modelFiles.forEach(file => {
   const sourcePath = path.join(sourceDir, file);
   const destPath = path.join(destDir, file);
-  
+
   if (fs.existsSync(sourcePath)) {
     fs.copyFileSync(sourcePath, destPath);
     console.log(`Copied ${file} to ${destPath}`);
@@ -25,4 +66,4 @@ modelFiles.forEach(file => {
   }
 });
 
-console.log('Model files copied successfully');
\ No newline at end of file
+console.log('Model files copied successfully');
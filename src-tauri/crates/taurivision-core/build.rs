@@ -0,0 +1,62 @@
+// Passes the resolved tract-core version to the model cache, whose entries
+// are only valid for the exact release that wrote them
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    let version = match find_lockfile() {
+        Some(lockfile) => {
+            println!("cargo:rerun-if-changed={}", lockfile.display());
+            let contents = fs::read_to_string(&lockfile).unwrap_or_default();
+            locked_versions(&contents, "tract-core")
+        }
+        None => None,
+    };
+
+    // Without a lockfile, entries are only reused by this very build
+    let version = version.unwrap_or_else(|| {
+        println!("cargo:warning=tract-core version not found in Cargo.lock");
+        let built = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        format!("unresolved-{}", built)
+    });
+    println!("cargo:rustc-env=TAURIVISION_TRACT_VERSION={}", version);
+}
+
+// The lockfile of the workspace being built. The target dir usually sits in
+// the workspace root, which also covers builds from other workspaces (fuzz).
+fn find_lockfile() -> Option<PathBuf> {
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR")?);
+    let manifest_dir = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR")?);
+    out_dir
+        .ancestors()
+        .chain(manifest_dir.ancestors())
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|path| path.is_file())
+}
+
+// Versions of `name` in a Cargo.lock, joined with '+' when there are several
+fn locked_versions(lockfile: &str, name: &str) -> Option<String> {
+    let name_line = format!("name = \"{}\"", name);
+    let mut versions: Vec<&str> = lockfile
+        .split("[[package]]")
+        .filter(|package| package.lines().any(|line| line.trim() == name_line))
+        .filter_map(|package| {
+            package
+                .lines()
+                .find_map(|line| line.trim().strip_prefix("version = "))
+                .map(|version| version.trim_matches('"'))
+        })
+        .collect();
+    if versions.is_empty() {
+        return None;
+    }
+
+    versions.sort_unstable();
+    versions.dedup();
+    Some(versions.join("+"))
+}
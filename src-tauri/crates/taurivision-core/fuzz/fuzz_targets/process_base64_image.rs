@@ -0,0 +1,10 @@
+// Arbitrary strings through data URL stripping, base64 decoding and decoding
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use taurivision_core::ImageProcessor;
+
+fuzz_target!(|data: &str| {
+    let image_processor = ImageProcessor::new();
+    let _ = image_processor.process_base64_image(data);
+});
@@ -0,0 +1,11 @@
+// Arbitrary bytes through format sniffing, header checks and decoding.
+// Any panic here would abort the app in release builds.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use taurivision_core::ImageProcessor;
+
+fuzz_target!(|data: &[u8]| {
+    let image_processor = ImageProcessor::new();
+    let _ = image_processor.process_bytes(data);
+});
@@ -0,0 +1,18 @@
+// Camera frames whose dimensions and buffer length disagree
+#![no_main]
+
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+use taurivision_core::ImageProcessor;
+
+#[derive(Arbitrary, Debug)]
+struct Frame {
+    width: u32,
+    height: u32,
+    rgba_data: Vec<u8>,
+}
+
+fuzz_target!(|frame: Frame| {
+    let image_processor = ImageProcessor::new();
+    let _ = image_processor.process_camera_frame(frame.width, frame.height, frame.rgba_data);
+});
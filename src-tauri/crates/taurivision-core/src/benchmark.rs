@@ -0,0 +1,259 @@
+use crate::image_processor::ImageProcessor;
+use crate::model_format::{LoadedModel, ModelFormat};
+use crate::model_manager::{ModelManager, INPUT_SIZE};
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct BenchmarkConfig {
+    // Model to benchmark; falls back to the platform default model path
+    pub model_path: Option<PathBuf>,
+    // Images to feed the model; synthetic inputs are used when empty
+    pub image_paths: Vec<PathBuf>,
+    pub warmup_iterations: usize,
+    pub iterations: usize,
+    pub batch_sizes: Vec<usize>,
+}
+
+impl Default for BenchmarkConfig {
+    fn default() -> Self {
+        Self {
+            model_path: None,
+            image_paths: Vec::new(),
+            warmup_iterations: 5,
+            iterations: 50,
+            batch_sizes: vec![1, 2, 4, 8],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LatencyStats {
+    pub iterations: usize,
+    pub mean_ms: f64,
+    pub min_ms: f64,
+    pub max_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ThroughputResult {
+    pub batch_size: usize,
+    pub load_ms: f64,
+    pub mean_batch_ms: f64,
+    pub images_per_sec: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BenchmarkReport {
+    pub model_path: String,
+    pub model_size_bytes: u64,
+    pub os: String,
+    pub arch: String,
+    pub input_source: String,
+    pub cold_load_ms: f64,
+    pub first_inference_ms: f64,
+    pub warm_latency: LatencyStats,
+    pub throughput: Vec<ThroughputResult>,
+    // Peak resident set size of the whole process, when the platform reports it
+    pub peak_memory_bytes: Option<u64>,
+}
+
+impl BenchmarkReport {
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize benchmark report")
+    }
+
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_json()?)
+            .with_context(|| format!("Failed to write benchmark report to {:?}", path))
+    }
+}
+
+pub fn run_benchmark(config: &BenchmarkConfig) -> Result<BenchmarkReport> {
+    if config.iterations == 0 {
+        anyhow::bail!("Benchmark needs at least one iteration");
+    }
+
+    let model_path = config
+        .model_path
+        .clone()
+        .unwrap_or_else(|| ModelManager::new().get_model_path());
+
+    info!("Benchmarking model at {:?}", model_path);
+
+    let inputs = load_inputs(&config.image_paths)?;
+    let input_source = if config.image_paths.is_empty() {
+        "synthetic".to_string()
+    } else {
+        format!("{} image(s)", inputs.len())
+    };
+
+    // Cold load covers reading the file, parsing, optimizing and planning the model
+    let cold_start = Instant::now();
+    let model_bytes = fs::read(&model_path)
+        .with_context(|| format!("Failed to read model file at {:?}", model_path))?;
+    let format = ModelFormat::detect(Some(&model_path), &model_bytes);
+    let model = LoadedModel::load(&model_bytes, format, 1)?;
+    let cold_load = cold_start.elapsed();
+
+    let first_start = Instant::now();
+    run_batch(&model, &inputs, 0, 1)?;
+    let first_inference = first_start.elapsed();
+
+    for i in 0..config.warmup_iterations {
+        run_batch(&model, &inputs, i, 1)?;
+    }
+
+    let mut samples = Vec::with_capacity(config.iterations);
+    for i in 0..config.iterations {
+        let start = Instant::now();
+        run_batch(&model, &inputs, i, 1)?;
+        samples.push(start.elapsed());
+    }
+    let warm_latency = latency_stats(&mut samples);
+
+    let mut throughput = Vec::with_capacity(config.batch_sizes.len());
+    for &batch_size in config.batch_sizes.iter().filter(|&&b| b > 0) {
+        if batch_size != model.batch_size && !format.can_rebatch() {
+            warn!(
+                "Skipping batch size {}, {:?} models have a fixed batch size",
+                batch_size, format
+            );
+            continue;
+        }
+
+        // Batch size is baked into the optimized model, so each size gets its own plan
+        let load_start = Instant::now();
+        let batch_model = LoadedModel::load(&model_bytes, format, batch_size)?;
+        let load_time = load_start.elapsed();
+
+        run_batch(&batch_model, &inputs, 0, batch_size)?;
+
+        let start = Instant::now();
+        for i in 0..config.iterations {
+            run_batch(&batch_model, &inputs, i * batch_size, batch_size)?;
+        }
+        let total = start.elapsed();
+
+        let mean_batch_ms = millis(total) / config.iterations as f64;
+        throughput.push(ThroughputResult {
+            batch_size,
+            load_ms: millis(load_time),
+            mean_batch_ms,
+            images_per_sec: (config.iterations * batch_size) as f64 / total.as_secs_f64(),
+        });
+    }
+
+    let report = BenchmarkReport {
+        model_path: model_path.to_string_lossy().into_owned(),
+        model_size_bytes: model_bytes.len() as u64,
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        input_source,
+        cold_load_ms: millis(cold_load),
+        first_inference_ms: millis(first_inference),
+        warm_latency,
+        throughput,
+        peak_memory_bytes: peak_memory_bytes(),
+    };
+
+    info!(
+        "Benchmark finished: cold load {:.1} ms, p50 {:.2} ms",
+        report.cold_load_ms, report.warm_latency.p50_ms
+    );
+
+    Ok(report)
+}
+
+// Preprocess the provided images, or generate one synthetic input
+fn load_inputs(image_paths: &[PathBuf]) -> Result<Vec<Vec<f32>>> {
+    if image_paths.is_empty() {
+        return Ok(vec![synthetic_input()]);
+    }
+
+    let image_processor = ImageProcessor::new();
+    image_paths
+        .iter()
+        .map(|path| image_processor.load_image(&path.to_string_lossy()))
+        .collect()
+}
+
+// Deterministic pseudo-random pixels in [0, 1] so runs are comparable
+fn synthetic_input() -> Vec<f32> {
+    let mut state: u32 = 0x2545_f491;
+    (0..INPUT_SIZE * INPUT_SIZE * 3)
+        .map(|_| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 8) as f32 / (1u32 << 24) as f32
+        })
+        .collect()
+}
+
+// Run one batch, cycling through the inputs starting at `offset`
+fn run_batch(
+    model: &LoadedModel,
+    inputs: &[Vec<f32>],
+    offset: usize,
+    batch_size: usize,
+) -> Result<()> {
+    let batch: Vec<&[f32]> = (0..batch_size)
+        .map(|i| inputs[(offset + i) % inputs.len()].as_slice())
+        .collect();
+
+    model.run(&batch).context("Benchmark inference failed")?;
+
+    Ok(())
+}
+
+pub(crate) fn latency_stats(samples: &mut [Duration]) -> LatencyStats {
+    samples.sort();
+
+    let total: Duration = samples.iter().sum();
+    LatencyStats {
+        iterations: samples.len(),
+        mean_ms: millis(total) / samples.len() as f64,
+        min_ms: millis(samples[0]),
+        max_ms: millis(samples[samples.len() - 1]),
+        p50_ms: millis(percentile(samples, 50.0)),
+        p95_ms: millis(percentile(samples, 95.0)),
+        p99_ms: millis(percentile(samples, 99.0)),
+    }
+}
+
+// Nearest-rank percentile over sorted samples
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+pub(crate) fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(unix)]
+fn peak_memory_bytes() -> Option<u64> {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return None;
+    }
+
+    // ru_maxrss is in bytes on Apple platforms and kilobytes everywhere else
+    if cfg!(any(target_os = "macos", target_os = "ios")) {
+        Some(usage.ru_maxrss as u64)
+    } else {
+        Some(usage.ru_maxrss as u64 * 1024)
+    }
+}
+
+#[cfg(not(unix))]
+fn peak_memory_bytes() -> Option<u64> {
+    None
+}
@@ -0,0 +1,61 @@
+// Headless benchmark runner for measuring models on the current device
+use anyhow::Result;
+use clap::Parser;
+use std::path::PathBuf;
+use taurivision_core::benchmark::{run_benchmark, BenchmarkConfig};
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "taurivision-bench",
+    about = "Benchmark an ONNX or TFLite model on this device"
+)]
+struct Args {
+    /// Model to benchmark (defaults to the bundled model path)
+    #[arg(short, long)]
+    model: Option<PathBuf>,
+
+    /// Images to use as inputs; synthetic data is used when none are given
+    #[arg(short, long = "image")]
+    images: Vec<PathBuf>,
+
+    /// Timed iterations per measurement
+    #[arg(short = 'n', long, default_value_t = 50)]
+    iterations: usize,
+
+    /// Untimed iterations before measuring warm latency
+    #[arg(short, long, default_value_t = 5)]
+    warmup: usize,
+
+    /// Batch sizes to measure throughput at
+    #[arg(short, long, value_delimiter = ',', default_values_t = [1, 2, 4, 8])]
+    batch_sizes: Vec<usize>,
+
+    /// Write the JSON report here instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let args = Args::parse();
+
+    let config = BenchmarkConfig {
+        model_path: args.model,
+        image_paths: args.images,
+        warmup_iterations: args.warmup,
+        iterations: args.iterations,
+        batch_sizes: args.batch_sizes,
+    };
+
+    let report = run_benchmark(&config)?;
+
+    match args.output {
+        Some(path) => {
+            report.write_json(&path)?;
+            println!("Benchmark report written to {:?}", path);
+        }
+        None => println!("{}", report.to_json()?),
+    }
+
+    Ok(())
+}
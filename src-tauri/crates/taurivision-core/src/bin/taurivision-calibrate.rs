@@ -0,0 +1,140 @@
+// Offline calibration and evaluation of quantized models
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use taurivision_core::calibration::{calibrate, compare_models, ComparisonConfig};
+use taurivision_core::image_processor::ImageProcessor;
+use taurivision_core::model_manager::ModelManager;
+use taurivision_core::scan::scan_dir;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "taurivision-calibrate",
+    about = "Prepare and check INT8 quantized models"
+)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+
+    /// Write the JSON report here instead of stdout
+    #[arg(short, long, global = true)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Collect input (and float model output) statistics from sample images
+    Stats {
+        /// Folder of representative sample images
+        images: PathBuf,
+
+        /// Float model whose output ranges to record as well
+        #[arg(short, long)]
+        model: Option<PathBuf>,
+
+        /// Descend into subdirectories
+        #[arg(short, long)]
+        recursive: bool,
+    },
+
+    /// Compare accuracy and latency of a quantized model with its float original
+    Compare {
+        /// Folder of evaluation images
+        images: PathBuf,
+
+        /// Float model (defaults to the bundled model path)
+        #[arg(long = "float")]
+        float_model: Option<PathBuf>,
+
+        /// Quantized model
+        #[arg(long = "quantized")]
+        quantized_model: PathBuf,
+
+        /// Labels file shared by both models
+        #[arg(short, long)]
+        labels: Option<PathBuf>,
+
+        /// Labels compared per image for the top-k overlap
+        #[arg(short = 'k', long, default_value_t = 5)]
+        top_k: usize,
+
+        /// Use each image's folder name as its true label to report accuracy
+        #[arg(long)]
+        labels_from_dirs: bool,
+
+        /// Descend into subdirectories
+        #[arg(short, long)]
+        recursive: bool,
+    },
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let args = Args::parse();
+    let image_processor = ImageProcessor::new();
+
+    match args.command {
+        Command::Stats {
+            images,
+            model,
+            recursive,
+        } => {
+            let image_paths = find_images(&images, recursive)?;
+            let report = calibrate(&image_paths, model.as_deref(), &image_processor)?;
+            write_report(&report, args.output)
+        }
+        Command::Compare {
+            images,
+            float_model,
+            quantized_model,
+            labels,
+            top_k,
+            labels_from_dirs,
+            recursive,
+        } => {
+            let model_manager = ModelManager::new();
+            let config = ComparisonConfig {
+                float_model: float_model.unwrap_or_else(|| model_manager.get_model_path()),
+                quantized_model,
+                labels: labels.unwrap_or_else(|| model_manager.get_labels_path()),
+                image_paths: find_images(&images, recursive || labels_from_dirs)?,
+                top_k,
+                labels_from_dirs,
+            };
+            let report = compare_models(&config, &image_processor)?;
+
+            eprintln!(
+                "top-1 agreement {:.1}%, p50 {:.2} ms -> {:.2} ms ({:.2}x)",
+                report.top1_agreement * 100.0,
+                report.float_model.latency.p50_ms,
+                report.quantized_model.latency.p50_ms,
+                report.speedup
+            );
+            write_report(&report, args.output)
+        }
+    }
+}
+
+fn find_images(dir: &Path, recursive: bool) -> Result<Vec<PathBuf>> {
+    let image_paths = scan_dir(dir, recursive)?;
+    if image_paths.is_empty() {
+        anyhow::bail!("No images found in {:?}", dir);
+    }
+    Ok(image_paths)
+}
+
+fn write_report<T: Serialize>(report: &T, output: Option<PathBuf>) -> Result<()> {
+    let json = serde_json::to_string_pretty(report)?;
+
+    match output {
+        Some(path) => {
+            fs::write(&path, json)?;
+            println!("Report written to {:?}", path);
+        }
+        None => println!("{}", json),
+    }
+
+    Ok(())
+}
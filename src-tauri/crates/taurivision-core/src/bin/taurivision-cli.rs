@@ -0,0 +1,217 @@
+// Headless command line front end for the recognition engine
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use taurivision_core::csv::csv_field;
+use taurivision_core::image_processor::{ImageProcessor, DEFAULT_MAX_DECODED_PIXELS};
+use taurivision_core::model_manager::{model_id_from_path, ModelManager};
+use taurivision_core::scan::{collect_dir, is_image};
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum OutputFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+#[derive(Parser, Debug)]
+#[command(name = "taurivision-cli", about = "Recognize images from the terminal")]
+struct Args {
+    /// Image files, glob patterns or directories
+    #[arg(required = true)]
+    inputs: Vec<String>,
+
+    /// ONNX or TFLite model or model package to load (defaults to the bundled
+    /// model path); a <model>.sha256 file next to it is verified when present
+    #[arg(short, long)]
+    model: Option<PathBuf>,
+
+    /// Refuse a --model without a package manifest or <model>.sha256 file.
+    /// The bundled model is always verified.
+    #[arg(long)]
+    require_checksum: bool,
+
+    /// Labels file matching the model outputs
+    #[arg(short, long)]
+    labels: Option<PathBuf>,
+
+    /// Number of results to report per image
+    #[arg(short = 'k', long, default_value_t = 5)]
+    top_k: usize,
+
+    /// Drop results with a confidence below this value
+    #[arg(short, long, default_value_t = 0.0)]
+    threshold: f32,
+
+    /// How to print the results
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+
+    /// Refuse images that would decode to more pixels than this
+    #[arg(long, default_value_t = DEFAULT_MAX_DECODED_PIXELS)]
+    max_pixels: u64,
+
+    /// Descend into subdirectories
+    #[arg(short, long)]
+    recursive: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct ImageResult {
+    path: String,
+    results: Vec<Prediction>,
+}
+
+#[derive(Serialize, Debug)]
+struct Prediction {
+    label: String,
+    confidence: f32,
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let args = Args::parse();
+
+    let mut model_manager = ModelManager::new();
+    let labels_path = args
+        .labels
+        .clone()
+        .unwrap_or_else(|| model_manager.get_labels_path());
+    match &args.model {
+        Some(model_path) => {
+            let model_id = model_id_from_path(model_path);
+            if args.require_checksum {
+                model_manager.register_verified(&model_id, model_path, &labels_path)?;
+            } else {
+                model_manager.register(&model_id, model_path, &labels_path)?;
+            }
+        }
+        None => model_manager.init_with_paths(model_manager.get_model_path(), labels_path)?,
+    }
+
+    let image_paths = collect_images(&args.inputs, args.recursive)?;
+    if image_paths.is_empty() {
+        anyhow::bail!("No images found in the given inputs");
+    }
+
+    let mut image_processor = ImageProcessor::new();
+    image_processor.set_max_decoded_pixels(args.max_pixels);
+    let mut outputs = Vec::with_capacity(image_paths.len());
+    let mut failures = 0;
+
+    for path in &image_paths {
+        let path_str = path.to_string_lossy().into_owned();
+        let recognized = image_processor
+            .load_image(&path_str)
+            .and_then(|data| model_manager.recognize_top_k(&data, args.top_k));
+
+        match recognized {
+            Ok(results) => outputs.push(ImageResult {
+                path: path_str,
+                results: results
+                    .into_iter()
+                    .filter(|(_, confidence)| *confidence >= args.threshold)
+                    .map(|(label, confidence)| Prediction { label, confidence })
+                    .collect(),
+            }),
+            Err(e) => {
+                eprintln!("{}: {:#}", path_str, e);
+                failures += 1;
+            }
+        }
+    }
+
+    match args.format {
+        OutputFormat::Table => print_table(&outputs),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&outputs)?),
+        OutputFormat::Csv => print_csv(&outputs),
+    }
+
+    if failures > 0 {
+        anyhow::bail!("{} of {} image(s) failed", failures, image_paths.len());
+    }
+
+    Ok(())
+}
+
+// Expand files, glob patterns and directories into a sorted list of image paths
+fn collect_images(inputs: &[String], recursive: bool) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+
+    for input in inputs {
+        let path = Path::new(input);
+        if path.is_dir() {
+            collect_dir(path, recursive, &mut paths)?;
+        } else if path.exists() {
+            paths.push(path.to_path_buf());
+        } else {
+            let matches =
+                glob::glob(input).with_context(|| format!("Invalid glob pattern: {}", input))?;
+            let before = paths.len();
+            for entry in matches {
+                let entry = entry.context("Failed to read glob match")?;
+                if entry.is_file() && is_image(&entry) {
+                    paths.push(entry);
+                }
+            }
+            if paths.len() == before {
+                eprintln!("No images matched: {}", input);
+            }
+        }
+    }
+
+    paths.sort();
+    paths.dedup();
+    Ok(paths)
+}
+
+fn print_table(outputs: &[ImageResult]) {
+    let label_width = outputs
+        .iter()
+        .flat_map(|output| output.results.iter())
+        .map(|prediction| prediction.label.len())
+        .max()
+        .unwrap_or(0)
+        .max("LABEL".len());
+
+    for output in outputs {
+        println!("{}", output.path);
+        if output.results.is_empty() {
+            println!("  (no results above threshold)");
+            continue;
+        }
+
+        println!(
+            "  {:>4}  {:<width$}  {:>10}",
+            "RANK",
+            "LABEL",
+            "CONFIDENCE",
+            width = label_width
+        );
+        for (rank, prediction) in output.results.iter().enumerate() {
+            println!(
+                "  {:>4}  {:<width$}  {:>10.4}",
+                rank + 1,
+                prediction.label,
+                prediction.confidence,
+                width = label_width
+            );
+        }
+    }
+}
+
+fn print_csv(outputs: &[ImageResult]) {
+    println!("path,rank,label,confidence");
+    for output in outputs {
+        for (rank, prediction) in output.results.iter().enumerate() {
+            println!(
+                "{},{},{},{}",
+                csv_field(&output.path),
+                rank + 1,
+                csv_field(&prediction.label),
+                prediction.confidence
+            );
+        }
+    }
+}
@@ -0,0 +1,39 @@
+// Describe a model file without running it: inputs, outputs, operators,
+// parameters and whether tract can load it
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::fs;
+use std::path::PathBuf;
+use taurivision_core::model_inspect::inspect;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "taurivision-inspect",
+    about = "Report the structure of an ONNX or TFLite model or model package"
+)]
+struct Args {
+    /// Model file or model package
+    model: PathBuf,
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let args = Args::parse();
+
+    let bytes =
+        fs::read(&args.model).with_context(|| format!("Failed to read {:?}", args.model))?;
+    let report = inspect(Some(&args.model), &bytes)?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+
+    if !report.unsupported_ops.is_empty() {
+        eprintln!(
+            "Unsupported operators: {}",
+            report.unsupported_ops.join(", ")
+        );
+    }
+    if let Some(load_error) = &report.load_error {
+        eprintln!("Model does not load: {}", load_error);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,88 @@
+// Local HTTP inference server exposing the recognition engine over REST
+use anyhow::Result;
+use clap::Parser;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use taurivision_core::model_manager::{model_id_from_path, ModelManager};
+use taurivision_core::server::{serve, ServerConfig};
+use taurivision_core::EnsembleConfig;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "taurivision-server",
+    about = "Serve image recognition over HTTP"
+)]
+struct Args {
+    /// Address to listen on
+    #[arg(short, long, default_value = "127.0.0.1:8080")]
+    bind: SocketAddr,
+
+    /// ONNX or TFLite model to register; repeat to serve several models (the first is the default).
+    /// A <model>.sha256 file next to it is verified when present
+    #[arg(short, long = "model")]
+    models: Vec<PathBuf>,
+
+    /// Refuse a --model without a package manifest or <model>.sha256 file.
+    /// The bundled model is always verified.
+    #[arg(long)]
+    require_checksum: bool,
+
+    /// Labels file for each --model, in the same order
+    #[arg(short, long = "labels")]
+    labels: Vec<PathBuf>,
+
+    /// Ensemble config (JSON) combining registered models under a new model id; repeatable
+    #[arg(long = "ensemble")]
+    ensembles: Vec<PathBuf>,
+
+    /// Maximum request body size in bytes
+    #[arg(long, default_value_t = 10 * 1024 * 1024)]
+    max_body_bytes: usize,
+
+    /// Maximum number of images in a batch request
+    #[arg(long, default_value_t = 16)]
+    max_batch_size: usize,
+
+    /// Results per image when the request does not ask for a count
+    #[arg(short = 'k', long, default_value_t = 5)]
+    top_k: usize,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+    let args = Args::parse();
+
+    let mut model_manager = ModelManager::new();
+
+    if args.models.is_empty() {
+        model_manager.init()?;
+    } else {
+        if args.labels.len() != args.models.len() {
+            anyhow::bail!("Pass one --labels file for every --model");
+        }
+
+        for (model_path, labels_path) in args.models.iter().zip(&args.labels) {
+            let model_id = model_id_from_path(model_path);
+            if args.require_checksum {
+                model_manager.register_verified(&model_id, model_path, labels_path)?;
+            } else {
+                model_manager.register(&model_id, model_path, labels_path)?;
+            }
+        }
+    }
+
+    for config_path in &args.ensembles {
+        let config = EnsembleConfig::load(config_path)?;
+        model_manager.register_ensemble(&config)?;
+    }
+
+    let config = ServerConfig {
+        bind_addr: args.bind,
+        max_body_bytes: args.max_body_bytes,
+        max_batch_size: args.max_batch_size,
+        default_top_k: args.top_k,
+    };
+
+    serve(model_manager, config).await
+}
@@ -0,0 +1,605 @@
+// Offline tooling for shipping quantized models: statistics of the
+// preprocessed sample inputs for an external quantizer, and a side-by-side
+// comparison of a quantized model against its float original.
+use crate::benchmark::{latency_stats, millis, LatencyStats};
+use crate::image_processor::ImageProcessor;
+use crate::model_format::{LoadedModel, ModelFormat, ModelPrecision};
+use crate::model_manager::{load_labels_from_path, top_k_labels, INPUT_SIZE};
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// Values kept per tensor for percentile estimates
+const RESERVOIR_SIZE: usize = 100_000;
+
+// Range clipping that ignores rare outliers, as common quantizers do
+const CLIP_LOW_PERCENTILE: f64 = 0.01;
+const CLIP_HIGH_PERCENTILE: f64 = 99.99;
+
+const CHANNELS: [&str; 3] = ["r", "g", "b"];
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TensorStats {
+    pub count: u64,
+    pub min: f32,
+    pub max: f32,
+    pub mean: f64,
+    pub std: f64,
+    pub p_low: f32,
+    pub p_high: f32,
+}
+
+// Affine uint8 parameters: real = scale * (q - zero_point)
+#[derive(Serialize, Debug, Clone)]
+pub struct QuantParams {
+    pub scale: f32,
+    pub zero_point: u8,
+}
+
+impl QuantParams {
+    fn for_range(min: f32, max: f32) -> Self {
+        // The range must contain zero so that zero is exactly representable
+        let min = min.min(0.0);
+        let max = max.max(0.0);
+        let scale = if max > min { (max - min) / 255.0 } else { 1.0 };
+        let zero_point = (-min / scale).round().clamp(0.0, 255.0) as u8;
+        Self { scale, zero_point }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ChannelStats {
+    pub channel: &'static str,
+    pub stats: TensorStats,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CalibrationReport {
+    pub images: usize,
+    pub failed: Vec<String>,
+    pub preprocessing: String,
+    pub input_shape: Vec<usize>,
+    pub input_channels: Vec<ChannelStats>,
+    pub input: TensorStats,
+    // Percentile-clipped input range
+    pub input_quant_params: QuantParams,
+    // Scores of the float model, when one was given
+    pub output: Option<TensorStats>,
+    pub output_quant_params: Option<QuantParams>,
+}
+
+// Run the sample images through `ImageProcessor`, and optionally the float
+// model, collecting the value ranges a post-training quantizer needs
+pub fn calibrate(
+    image_paths: &[PathBuf],
+    model_path: Option<&Path>,
+    image_processor: &ImageProcessor,
+) -> Result<CalibrationReport> {
+    let model = match model_path {
+        Some(path) => Some(load_model_file(path)?.0),
+        None => None,
+    };
+
+    let mut channels: Vec<StatsAccumulator> = CHANNELS
+        .iter()
+        .enumerate()
+        .map(|(index, _)| StatsAccumulator::new(index as u64 + 1))
+        .collect();
+    let mut input = StatsAccumulator::new(0);
+    let mut output = StatsAccumulator::new(CHANNELS.len() as u64 + 1);
+    let mut images = 0;
+    let mut failed = Vec::new();
+
+    for path in image_paths {
+        let data = match image_processor.load_image(&path.to_string_lossy()) {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("Skipping {:?}: {:#}", path, e);
+                failed.push(path.to_string_lossy().into_owned());
+                continue;
+            }
+        };
+
+        // Preprocessed data is HWC, so channels interleave
+        for (index, value) in data.iter().enumerate() {
+            channels[index % CHANNELS.len()].push(*value);
+            input.push(*value);
+        }
+
+        if let Some(model) = &model {
+            let scores = model
+                .run(&[data.as_slice()])
+                .with_context(|| format!("Inference failed for {:?}", path))?;
+            for score in scores.into_iter().flatten() {
+                output.push(score);
+            }
+        }
+
+        images += 1;
+    }
+
+    if images == 0 {
+        anyhow::bail!(
+            "None of the {} calibration image(s) could be read",
+            image_paths.len()
+        );
+    }
+    info!("Collected calibration statistics from {} image(s)", images);
+
+    let input = input.finish();
+    let input_quant_params = QuantParams::for_range(input.p_low, input.p_high);
+    let output = model.is_some().then(|| output.finish());
+    let output_quant_params = output
+        .as_ref()
+        .map(|stats| QuantParams::for_range(stats.p_low, stats.p_high));
+
+    Ok(CalibrationReport {
+        images,
+        failed,
+        preprocessing: image_processor.profile(),
+        input_shape: model
+            .as_ref()
+            .map(LoadedModel::input_shape)
+            .unwrap_or_else(|| vec![1, 3, INPUT_SIZE, INPUT_SIZE]),
+        input_channels: CHANNELS
+            .iter()
+            .zip(channels)
+            .map(|(&channel, stats)| ChannelStats {
+                channel,
+                stats: stats.finish(),
+            })
+            .collect(),
+        input,
+        input_quant_params,
+        output,
+        output_quant_params,
+    })
+}
+
+// Running min/max/mean/variance (Welford) plus a reservoir sample for percentiles
+struct StatsAccumulator {
+    count: u64,
+    min: f32,
+    max: f32,
+    mean: f64,
+    m2: f64,
+    reservoir: Vec<f32>,
+    rng: u64,
+}
+
+impl StatsAccumulator {
+    fn new(seed: u64) -> Self {
+        Self {
+            count: 0,
+            min: f32::INFINITY,
+            max: f32::NEG_INFINITY,
+            mean: 0.0,
+            m2: 0.0,
+            reservoir: Vec::with_capacity(RESERVOIR_SIZE),
+            // Fixed seeds keep reports reproducible
+            rng: 0x9e37_79b9_7f4a_7c15 ^ seed,
+        }
+    }
+
+    fn push(&mut self, value: f32) {
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+
+        let delta = value as f64 - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value as f64 - self.mean);
+
+        if self.reservoir.len() < RESERVOIR_SIZE {
+            self.reservoir.push(value);
+        } else {
+            let slot = (self.next_random() % self.count) as usize;
+            if slot < RESERVOIR_SIZE {
+                self.reservoir[slot] = value;
+            }
+        }
+    }
+
+    // xorshift64, good enough for sampling
+    fn next_random(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+
+    fn finish(mut self) -> TensorStats {
+        self.reservoir.sort_by(f32::total_cmp);
+
+        TensorStats {
+            count: self.count,
+            min: self.min,
+            max: self.max,
+            mean: self.mean,
+            std: if self.count > 1 {
+                (self.m2 / (self.count - 1) as f64).sqrt()
+            } else {
+                0.0
+            },
+            p_low: sample_percentile(&self.reservoir, CLIP_LOW_PERCENTILE),
+            p_high: sample_percentile(&self.reservoir, CLIP_HIGH_PERCENTILE),
+        }
+    }
+}
+
+// Nearest-rank percentile over sorted values
+fn sample_percentile(sorted: &[f32], p: f64) -> f32 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ModelSummary {
+    pub path: String,
+    pub size_bytes: u64,
+    pub format: ModelFormat,
+    pub precision: ModelPrecision,
+    pub load_ms: f64,
+    pub latency: LatencyStats,
+    // Share of images whose top-1 label matches the expected label
+    pub accuracy: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Disagreement {
+    pub path: String,
+    pub float_label: String,
+    pub quantized_label: String,
+    pub expected_label: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ComparisonReport {
+    pub images: usize,
+    pub failed: Vec<String>,
+    pub top_k: usize,
+    pub float_model: ModelSummary,
+    pub quantized_model: ModelSummary,
+    // Share of images where both models agree on the top-1 label
+    pub top1_agreement: f64,
+    // Mean share of top-k labels the models have in common
+    pub top_k_overlap: f64,
+    pub mean_abs_score_diff: f64,
+    pub max_abs_score_diff: f32,
+    // Float p50 latency over quantized p50 latency
+    pub speedup: f64,
+    pub size_ratio: f64,
+    pub disagreements: Vec<Disagreement>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ComparisonConfig {
+    pub float_model: PathBuf,
+    pub quantized_model: PathBuf,
+    pub labels: PathBuf,
+    pub image_paths: Vec<PathBuf>,
+    pub top_k: usize,
+    // Treat each image's parent directory name as its true label
+    pub labels_from_dirs: bool,
+}
+
+// Run both models on every image and report how far the quantized model
+// drifts from the float model, and how much faster it is
+pub fn compare_models(
+    config: &ComparisonConfig,
+    image_processor: &ImageProcessor,
+) -> Result<ComparisonReport> {
+    if config.top_k == 0 {
+        anyhow::bail!("top_k must be at least 1");
+    }
+
+    let labels = load_labels_from_path(&config.labels)?;
+    let (float_model, float_size, float_load) = load_model_file(&config.float_model)?;
+    let (quantized_model, quantized_size, quantized_load) =
+        load_model_file(&config.quantized_model)?;
+    if quantized_model.precision != ModelPrecision::Int8 {
+        warn!(
+            "{:?} contains no quantized operators",
+            config.quantized_model
+        );
+    }
+
+    let mut float_times = Vec::with_capacity(config.image_paths.len());
+    let mut quantized_times = Vec::with_capacity(config.image_paths.len());
+    let mut failed = Vec::new();
+    let mut disagreements = Vec::new();
+    let mut agreements = 0;
+    let mut overlap_sum = 0.0;
+    let mut diff_sum = 0.0;
+    let mut diff_count = 0u64;
+    let mut max_diff = 0.0f32;
+    let mut labeled = 0;
+    let mut float_correct = 0;
+    let mut quantized_correct = 0;
+
+    for path in &config.image_paths {
+        let data = match image_processor.load_image(&path.to_string_lossy()) {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("Skipping {:?}: {:#}", path, e);
+                failed.push(path.to_string_lossy().into_owned());
+                continue;
+            }
+        };
+
+        let (float_scores, float_time) = run_timed(&float_model, &data)?;
+        let (quantized_scores, quantized_time) = run_timed(&quantized_model, &data)?;
+        float_times.push(float_time);
+        quantized_times.push(quantized_time);
+
+        for (a, b) in float_scores.iter().zip(&quantized_scores) {
+            let diff = (a - b).abs();
+            diff_sum += diff as f64;
+            diff_count += 1;
+            max_diff = max_diff.max(diff);
+        }
+
+        let float_top = top_k_labels(float_scores, &labels, config.top_k);
+        let quantized_top = top_k_labels(quantized_scores, &labels, config.top_k);
+        let shared = float_top
+            .iter()
+            .filter(|(label, _)| quantized_top.iter().any(|(other, _)| other == label))
+            .count();
+        overlap_sum += shared as f64 / config.top_k as f64;
+
+        let float_label = top_label(&float_top);
+        let quantized_label = top_label(&quantized_top);
+        let expected_label = config
+            .labels_from_dirs
+            .then(|| parent_dir_name(path))
+            .flatten();
+
+        if let Some(expected) = &expected_label {
+            labeled += 1;
+            float_correct += usize::from(float_label.eq_ignore_ascii_case(expected));
+            quantized_correct += usize::from(quantized_label.eq_ignore_ascii_case(expected));
+        }
+
+        if float_label == quantized_label {
+            agreements += 1;
+        } else {
+            disagreements.push(Disagreement {
+                path: path.to_string_lossy().into_owned(),
+                float_label,
+                quantized_label,
+                expected_label,
+            });
+        }
+    }
+
+    let images = float_times.len();
+    if images == 0 {
+        anyhow::bail!(
+            "None of the {} image(s) could be read",
+            config.image_paths.len()
+        );
+    }
+
+    let accuracy = |correct: usize| (labeled > 0).then(|| correct as f64 / labeled as f64);
+    let float_model = ModelSummary {
+        path: config.float_model.to_string_lossy().into_owned(),
+        size_bytes: float_size,
+        format: float_model.format,
+        precision: float_model.precision,
+        load_ms: millis(float_load),
+        latency: latency_stats(&mut float_times),
+        accuracy: accuracy(float_correct),
+    };
+    let quantized_model = ModelSummary {
+        path: config.quantized_model.to_string_lossy().into_owned(),
+        size_bytes: quantized_size,
+        format: quantized_model.format,
+        precision: quantized_model.precision,
+        load_ms: millis(quantized_load),
+        latency: latency_stats(&mut quantized_times),
+        accuracy: accuracy(quantized_correct),
+    };
+
+    Ok(ComparisonReport {
+        images,
+        failed,
+        top_k: config.top_k,
+        top1_agreement: agreements as f64 / images as f64,
+        top_k_overlap: overlap_sum / images as f64,
+        mean_abs_score_diff: if diff_count > 0 {
+            diff_sum / diff_count as f64
+        } else {
+            0.0
+        },
+        max_abs_score_diff: max_diff,
+        speedup: float_model.latency.p50_ms / quantized_model.latency.p50_ms,
+        size_ratio: quantized_model.size_bytes as f64 / float_model.size_bytes as f64,
+        float_model,
+        quantized_model,
+        disagreements,
+    })
+}
+
+fn load_model_file(path: &Path) -> Result<(LoadedModel, u64, Duration)> {
+    let start = Instant::now();
+    let model_bytes =
+        fs::read(path).with_context(|| format!("Failed to read model file at {:?}", path))?;
+    let format = ModelFormat::detect(Some(path), &model_bytes);
+    let model = LoadedModel::load(&model_bytes, format, 1)
+        .with_context(|| format!("Failed to load {:?}", path))?;
+    Ok((model, model_bytes.len() as u64, start.elapsed()))
+}
+
+fn run_timed(model: &LoadedModel, data: &[f32]) -> Result<(Vec<f32>, Duration)> {
+    let start = Instant::now();
+    let scores = model.run(&[data]).context("Comparison inference failed")?;
+    let elapsed = start.elapsed();
+    Ok((scores.into_iter().next().unwrap_or_default(), elapsed))
+}
+
+fn top_label(results: &[(String, f32)]) -> String {
+    results
+        .first()
+        .map(|(label, _)| label.clone())
+        .unwrap_or_default()
+}
+
+fn parent_dir_name(path: &Path) -> Option<String> {
+    path.parent()
+        .and_then(|dir| dir.file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_models::{float_model, quantized_model, LABELS};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "taurivision-calibration-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_solid_png(path: &Path, rgb: [u8; 3]) -> PathBuf {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        image::RgbImage::from_pixel(32, 32, image::Rgb(rgb))
+            .save(path)
+            .unwrap();
+        path.to_path_buf()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn accumulates_stats_of_a_known_sequence() {
+        let mut stats = StatsAccumulator::new(0);
+        for value in 1..=1000 {
+            stats.push(value as f32);
+        }
+        let stats = stats.finish();
+
+        assert_eq!(stats.count, 1000);
+        assert_eq!((stats.min, stats.max), (1.0, 1000.0));
+        assert_close(stats.mean, 500.5);
+        // Sample standard deviation of 1..=n is sqrt(n(n+1)/12)
+        assert_close(stats.std, (1000.0f64 * 1001.0 / 12.0).sqrt());
+        assert_eq!((stats.p_low, stats.p_high), (1.0, 1000.0));
+    }
+
+    #[test]
+    fn quant_params_keep_zero_representable() {
+        let mixed = QuantParams::for_range(-1.0, 3.0);
+        assert_close(mixed.scale as f64, 4.0 / 255.0);
+        assert_eq!(mixed.zero_point, 64);
+
+        // A positive range is widened down to zero
+        let positive = QuantParams::for_range(0.25, 1.0);
+        assert_close(positive.scale as f64, 1.0 / 255.0);
+        assert_eq!(positive.zero_point, 0);
+
+        assert_eq!(QuantParams::for_range(0.0, 0.0).scale, 1.0);
+    }
+
+    #[test]
+    fn calibrates_on_synthetic_images() {
+        let dir = temp_dir("calibrate");
+        let model_path = dir.join("model.onnx");
+        fs::write(&model_path, float_model()).unwrap();
+        let broken = dir.join("broken.png");
+        fs::write(&broken, b"not an image").unwrap();
+        let images = vec![
+            write_solid_png(&dir.join("a.png"), [255, 0, 51]),
+            broken.clone(),
+            write_solid_png(&dir.join("b.png"), [0, 255, 51]),
+        ];
+
+        let report = calibrate(&images, Some(&model_path), &ImageProcessor::new()).unwrap();
+        assert_eq!(report.images, 2);
+        assert_eq!(report.failed, vec![broken.to_string_lossy().into_owned()]);
+        assert_eq!(report.input_shape, vec![1, 3, INPUT_SIZE, INPUT_SIZE]);
+
+        let pixels = (INPUT_SIZE * INPUT_SIZE) as u64;
+        assert_eq!(report.input.count, 2 * 3 * pixels);
+        let [red, green, blue] = &report.input_channels[..] else {
+            panic!("expected three channels");
+        };
+        assert_eq!(red.channel, "r");
+        assert_eq!(red.stats.count, 2 * pixels);
+        assert_close(red.stats.mean, 0.5);
+        assert_eq!((green.stats.min, green.stats.max), (0.0, 1.0));
+        assert_close(blue.stats.mean, 0.2);
+        assert_close(blue.stats.std, 0.0);
+
+        // One score per label and image, the channel means
+        let output = report.output.unwrap();
+        assert_eq!(output.count, 2 * LABELS.len() as u64);
+        assert_close(output.mean, 0.4);
+        assert_eq!(report.output_quant_params.unwrap().zero_point, 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn compares_float_and_quantized_models() {
+        let dir = temp_dir("compare");
+        let float_path = dir.join("float.onnx");
+        let quantized_path = dir.join("quantized.onnx");
+        let labels_path = dir.join("labels.txt");
+        fs::write(&float_path, float_model()).unwrap();
+        fs::write(&quantized_path, quantized_model(0.5)).unwrap();
+        fs::write(&labels_path, LABELS.join("\n")).unwrap();
+
+        // Rounded to steps of 0.5, green (0.7) ties with red (0.6) and loses
+        let close_call = write_solid_png(&dir.join("green/a.png"), [153, 179, 0]);
+        let clear = write_solid_png(&dir.join("red/b.png"), [255, 0, 0]);
+
+        let config = ComparisonConfig {
+            float_model: float_path,
+            quantized_model: quantized_path,
+            labels: labels_path,
+            image_paths: vec![close_call.clone(), clear],
+            top_k: 2,
+            labels_from_dirs: true,
+        };
+        let report = compare_models(&config, &ImageProcessor::new()).unwrap();
+
+        assert_eq!(report.images, 2);
+        assert!(report.failed.is_empty());
+        assert_eq!(report.float_model.precision, ModelPrecision::Float);
+        assert_eq!(report.quantized_model.precision, ModelPrecision::Int8);
+        assert_close(report.top1_agreement, 0.5);
+        assert_close(report.top_k_overlap, 1.0);
+        assert_close(report.max_abs_score_diff as f64, 179.0 / 255.0 - 0.5);
+        assert_eq!(report.float_model.accuracy, Some(1.0));
+        assert_eq!(report.quantized_model.accuracy, Some(0.5));
+
+        let [disagreement] = &report.disagreements[..] else {
+            panic!("expected one disagreement");
+        };
+        assert_eq!(disagreement.path, close_call.to_string_lossy());
+        assert_eq!(disagreement.float_label, "green");
+        assert_eq!(disagreement.quantized_label, "red");
+        assert_eq!(disagreement.expected_label.as_deref(), Some("green"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
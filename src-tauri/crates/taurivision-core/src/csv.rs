@@ -0,0 +1,33 @@
+// CSV output shared by the CLI and the app's history export
+
+// Quote a field when it contains separators, quotes or line breaks
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_fields_are_unquoted() {
+        assert_eq!(csv_field("tabby cat"), "tabby cat");
+        assert_eq!(csv_field(""), "");
+    }
+
+    #[test]
+    fn special_characters_are_quoted() {
+        assert_eq!(csv_field("tabby, tabby cat"), "\"tabby, tabby cat\"");
+        assert_eq!(csv_field("line\nbreak"), "\"line\nbreak\"");
+        assert_eq!(csv_field("carriage\rreturn"), "\"carriage\rreturn\"");
+    }
+
+    #[test]
+    fn quotes_are_doubled() {
+        assert_eq!(csv_field("12\" vinyl"), "\"12\"\" vinyl\"");
+    }
+}
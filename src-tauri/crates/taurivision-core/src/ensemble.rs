@@ -0,0 +1,343 @@
+// Ensembles combine several registered classifiers into one virtual model.
+// Members keep running as registered; the ensemble maps their outputs onto
+// a shared label space and merges the probabilities.
+use crate::hash::sha256_hex;
+use crate::model_manager::{top_k_labels, ModelHandle};
+use anyhow::{Context, Result};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CombineMethod {
+    // Weighted mean of the members' probabilities
+    #[default]
+    Average,
+    // Each member votes for its top-1 label with its weight; ties go to the
+    // label with the higher average probability
+    Vote,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EnsembleMember {
+    pub model_id: String,
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+    // Label map file translating this member's labels into the ensemble's
+    #[serde(default)]
+    pub label_map: Option<PathBuf>,
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EnsembleConfig {
+    // Virtual model id the ensemble is registered under
+    pub id: String,
+    #[serde(default)]
+    pub method: CombineMethod,
+    pub members: Vec<EnsembleMember>,
+}
+
+impl EnsembleConfig {
+    // JSON file; relative label map paths are resolved against its directory
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read ensemble config {:?}", path))?;
+        let mut config: Self = serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid ensemble config {:?}", path))?;
+
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for member in &mut config.members {
+            if let Some(label_map) = member.label_map.as_mut() {
+                if label_map.is_relative() {
+                    *label_map = dir.join(&*label_map);
+                }
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+// Translates member labels into ensemble labels. Labels it does not mention
+// keep their name, so label spaces that mostly match only list the differences.
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct LabelMap {
+    labels: BTreeMap<String, String>,
+}
+
+impl LabelMap {
+    // JSON object of ensemble label to member labels: {"tabby cat": ["tabby", "tabby, tabby cat"]}
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read label map {:?}", path))?;
+        Self::parse(&contents).with_context(|| format!("Invalid label map {:?}", path))
+    }
+
+    pub fn parse(contents: &str) -> Result<Self> {
+        let grouped: HashMap<String, Vec<String>> = serde_json::from_str(contents)?;
+
+        let mut labels = BTreeMap::new();
+        for (target, sources) in grouped {
+            for source in sources {
+                if let Some(previous) = labels.insert(source.to_lowercase(), target.clone()) {
+                    if previous != target {
+                        anyhow::bail!("{} is mapped to both {} and {}", source, previous, target);
+                    }
+                }
+            }
+        }
+
+        Ok(Self { labels })
+    }
+
+    pub fn target<'a>(&'a self, label: &'a str) -> &'a str {
+        self.labels
+            .get(&label.to_lowercase())
+            .map(String::as_str)
+            .unwrap_or(label)
+    }
+}
+
+struct Member {
+    handle: ModelHandle,
+    weight: f32,
+    // Ensemble label index of each of the member's outputs
+    targets: Vec<usize>,
+}
+
+// An ensemble bound to the members registered when it was built
+pub(crate) struct Ensemble {
+    pub(crate) config: EnsembleConfig,
+    // Loaded label maps, kept to rebuild the ensemble when a member is replaced
+    pub(crate) label_maps: Vec<Option<LabelMap>>,
+    members: Vec<Member>,
+    labels: Vec<String>,
+}
+
+impl Ensemble {
+    // `handles` and `label_maps` are in the order of `config.members`
+    pub(crate) fn build(
+        config: EnsembleConfig,
+        label_maps: Vec<Option<LabelMap>>,
+        handles: Vec<ModelHandle>,
+    ) -> Self {
+        // The label space is the union of the mapped member labels, in order of appearance
+        let mut labels: Vec<String> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        let mut members = Vec::with_capacity(handles.len());
+
+        for ((member, label_map), handle) in config.members.iter().zip(&label_maps).zip(handles) {
+            let mut targets = Vec::with_capacity(handle.labels().len());
+            let mut shared = 0;
+            for label in handle.labels() {
+                let target = match label_map {
+                    Some(label_map) => label_map.target(label),
+                    None => label,
+                };
+                let position = match index.get(target) {
+                    Some(&position) => {
+                        shared += 1;
+                        position
+                    }
+                    None => {
+                        labels.push(target.to_string());
+                        index.insert(target.to_string(), labels.len() - 1);
+                        labels.len() - 1
+                    }
+                };
+                targets.push(position);
+            }
+
+            if !members.is_empty() && shared == 0 {
+                warn!(
+                    "Ensemble {}: {} shares no labels with the other members; add a label map",
+                    config.id, member.model_id
+                );
+            }
+            members.push(Member {
+                handle,
+                weight: member.weight,
+                targets,
+            });
+        }
+
+        Self {
+            config,
+            label_maps,
+            members,
+            labels,
+        }
+    }
+
+    pub(crate) fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    pub(crate) fn members(&self) -> impl Iterator<Item = &ModelHandle> {
+        self.members.iter().map(|member| &member.handle)
+    }
+
+    // Changes whenever a member model, weight or label map changes, so
+    // cached results of an older ensemble are not reused
+    pub(crate) fn sha256(&self) -> String {
+        let members: Vec<_> = self
+            .config
+            .members
+            .iter()
+            .zip(&self.members)
+            .zip(&self.label_maps)
+            .map(|((member, bound), label_map)| {
+                (
+                    &member.model_id,
+                    &bound.handle.info().sha256,
+                    member.weight,
+                    label_map,
+                )
+            })
+            .collect();
+        let description = serde_json::to_vec(&(self.config.method, members)).unwrap_or_default();
+        sha256_hex(&description)
+    }
+
+    pub(crate) fn recognize(&self, image_data: &[f32], top_k: usize) -> Result<Vec<(String, f32)>> {
+        let total_weight: f32 = self.members.iter().map(|member| member.weight).sum();
+
+        let mut outputs = Vec::with_capacity(self.members.len());
+        for member in &self.members {
+            let probabilities = member
+                .handle
+                .probabilities(image_data)
+                .with_context(|| format!("Ensemble member {} failed", member.handle.info().id))?;
+            outputs.push((
+                member.weight / total_weight,
+                map_scores(&member.targets, &probabilities, self.labels.len()),
+            ));
+        }
+
+        Ok(combine(self.config.method, &self.labels, &outputs, top_k))
+    }
+}
+
+// A member's probabilities on the ensemble labels. Several member labels may
+// map onto one ensemble label.
+fn map_scores(targets: &[usize], probabilities: &[f32], num_labels: usize) -> Vec<f32> {
+    let mut mapped = vec![0.0f32; num_labels];
+    for (&target, probability) in targets.iter().zip(probabilities) {
+        mapped[target] += probability;
+    }
+    mapped
+}
+
+// `outputs` holds each member's mapped probabilities with its share of the total weight
+fn combine(
+    method: CombineMethod,
+    labels: &[String],
+    outputs: &[(f32, Vec<f32>)],
+    top_k: usize,
+) -> Vec<(String, f32)> {
+    let mut average = vec![0.0f32; labels.len()];
+    let mut votes = vec![0.0f32; labels.len()];
+
+    for (weight, mapped) in outputs {
+        for (sum, probability) in average.iter_mut().zip(mapped) {
+            *sum += weight * probability;
+        }
+        if let Some(top) = (0..mapped.len()).max_by(|&a, &b| mapped[a].total_cmp(&mapped[b])) {
+            votes[top] += weight;
+        }
+    }
+
+    match method {
+        CombineMethod::Average => top_k_labels(average, labels, top_k),
+        CombineMethod::Vote => {
+            let mut voted: Vec<usize> = (0..votes.len()).filter(|&i| votes[i] > 0.0).collect();
+            voted.sort_by(|&a, &b| {
+                votes[b]
+                    .total_cmp(&votes[a])
+                    .then(average[b].total_cmp(&average[a]))
+            });
+            voted.truncate(top_k);
+            voted
+                .into_iter()
+                .map(|i| (labels[i].clone(), votes[i]))
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn assert_scores(actual: &[(String, f32)], expected: &[(&str, f32)]) {
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+        for ((label, score), (expected_label, expected_score)) in actual.iter().zip(expected) {
+            assert_eq!(label, expected_label, "{:?}", actual);
+            assert!((score - expected_score).abs() < 1e-6, "{:?}", actual);
+        }
+    }
+
+    #[test]
+    fn maps_member_labels_onto_ensemble_labels() {
+        // Member outputs "tabby" and "tiger cat" both count as ensemble label 0
+        let mapped = map_scores(&[0, 0, 2], &[0.2, 0.3, 0.5], 3);
+        assert_eq!(mapped, vec![0.5, 0.0, 0.5]);
+    }
+
+    #[test]
+    fn averages_weighted_probabilities() {
+        let labels = labels(&["cat", "dog", "fish"]);
+        let outputs = [(0.25, vec![0.8, 0.2, 0.0]), (0.75, vec![0.2, 0.6, 0.2])];
+
+        let combined = combine(CombineMethod::Average, &labels, &outputs, 5);
+        assert_scores(&combined, &[("dog", 0.5), ("cat", 0.35), ("fish", 0.15)]);
+
+        let top = combine(CombineMethod::Average, &labels, &outputs, 1);
+        assert_scores(&top, &[("dog", 0.5)]);
+    }
+
+    #[test]
+    fn votes_with_member_weights() {
+        let labels = labels(&["cat", "dog", "fish"]);
+        let outputs = [(0.25, vec![0.8, 0.2, 0.0]), (0.75, vec![0.2, 0.6, 0.2])];
+
+        // Labels no member picked get no votes and are left out
+        let combined = combine(CombineMethod::Vote, &labels, &outputs, 5);
+        assert_scores(&combined, &[("dog", 0.75), ("cat", 0.25)]);
+    }
+
+    #[test]
+    fn vote_ties_go_to_higher_average() {
+        let labels = labels(&["cat", "dog"]);
+        let outputs = [(0.5, vec![0.6, 0.4]), (0.5, vec![0.3, 0.7])];
+
+        let combined = combine(CombineMethod::Vote, &labels, &outputs, 5);
+        assert_scores(&combined, &[("dog", 0.5), ("cat", 0.5)]);
+    }
+
+    #[test]
+    fn label_maps_are_case_insensitive() {
+        let map = LabelMap::parse(r#"{"cat": ["Tabby", "tiger cat"]}"#).unwrap();
+        assert_eq!(map.target("tabby"), "cat");
+        assert_eq!(map.target("Tiger Cat"), "cat");
+        assert_eq!(map.target("dog"), "dog");
+    }
+
+    #[test]
+    fn label_maps_reject_conflicting_targets() {
+        assert!(LabelMap::parse(r#"{"cat": ["tabby"], "tiger": ["Tabby"]}"#).is_err());
+        // Listing a label twice for the same target is fine
+        assert!(LabelMap::parse(r#"{"cat": ["tabby", "Tabby"]}"#).is_ok());
+    }
+}
@@ -0,0 +1,478 @@
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use image::codecs::jpeg::JpegDecoder;
+use image::io::{Limits, Reader as ImageReader};
+use image::{
+    DynamicImage, GenericImageView, ImageBuffer, ImageDecoder, ImageFormat, ImageOutputFormat, Rgba,
+};
+use log::debug;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Read, Seek, SeekFrom};
+use thiserror::Error;
+
+// Default cap on decoded pixels (24 MP, ~96 MB as RGBA)
+pub const DEFAULT_MAX_DECODED_PIXELS: u64 = 24_000_000;
+
+// Default cap on encoded image size
+pub const DEFAULT_MAX_IMAGE_BYTES: u64 = 50 * 1024 * 1024;
+
+#[derive(Error, Debug)]
+pub enum ImageInputError {
+    #[error("Image too large: {width}x{height} exceeds the limit of {max_pixels} decoded pixels")]
+    TooLarge {
+        width: u32,
+        height: u32,
+        max_pixels: u64,
+    },
+
+    #[error("Image data too large: {size} bytes exceeds the limit of {max_bytes} bytes")]
+    TooManyBytes { size: u64, max_bytes: u64 },
+
+    #[error("Image dimensions {width}x{height} exceed the limit of {max_width}x{max_height}")]
+    DimensionsTooLarge {
+        width: u32,
+        height: u32,
+        max_width: u32,
+        max_height: u32,
+    },
+
+    #[error("Image has no pixels ({width}x{height})")]
+    Empty { width: u32, height: u32 },
+
+    #[error("Image format not allowed: {0}")]
+    FormatNotAllowed(String),
+
+    #[error("Camera frame of {width}x{height} needs {expected} RGBA bytes, got {actual}")]
+    InvalidFrame {
+        width: u32,
+        height: u32,
+        expected: u64,
+        actual: u64,
+    },
+}
+
+// Limits applied to every image before and during decoding
+#[derive(Debug, Clone)]
+pub struct InputLimits {
+    // Largest encoded input accepted (file size or decoded base64 size)
+    pub max_bytes: u64,
+    pub max_width: u32,
+    pub max_height: u32,
+    pub max_decoded_pixels: u64,
+    pub allowed_formats: Vec<ImageFormat>,
+}
+
+impl Default for InputLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_MAX_IMAGE_BYTES,
+            max_width: 16_384,
+            max_height: 16_384,
+            max_decoded_pixels: DEFAULT_MAX_DECODED_PIXELS,
+            allowed_formats: vec![
+                ImageFormat::Jpeg,
+                ImageFormat::Png,
+                ImageFormat::Gif,
+                ImageFormat::WebP,
+                ImageFormat::Bmp,
+                ImageFormat::Tiff,
+            ],
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ImageProcessor {
+    target_width: u32,
+    target_height: u32,
+    limits: InputLimits,
+}
+
+impl ImageProcessor {
+    pub fn new() -> Self {
+        // Default target size for the model input (mobilenet expects 224x224)
+        Self {
+            target_width: 224,
+            target_height: 224,
+            limits: InputLimits::default(),
+        }
+    }
+
+    // Load an image from a file path
+    pub fn load_image(&self, path: &str) -> Result<Vec<f32>> {
+        let img = self.decode_file(path)?;
+        self.preprocess_image(&img)
+    }
+
+    // Process base64-encoded image data
+    pub fn process_base64_image(&self, base64_data: &str) -> Result<Vec<f32>> {
+        let image_data = self.decode_base64(base64_data)?;
+        self.process_bytes(&image_data)
+    }
+
+    // Process encoded image bytes (JPEG, PNG, ...)
+    pub fn process_bytes(&self, image_data: &[u8]) -> Result<Vec<f32>> {
+        let img = self.decode_bytes(image_data)?;
+        self.preprocess_image(&img)
+    }
+
+    // Read an encoded image file, rejecting files over the byte limit up front
+    pub fn read_file(&self, path: &str) -> Result<Vec<u8>> {
+        let mut file = File::open(path)
+            .with_context(|| format!("Failed to open image from path: {}", path))?;
+        let size = file
+            .metadata()
+            .with_context(|| format!("Failed to read metadata for: {}", path))?
+            .len();
+        self.check_size(size)?;
+
+        let mut image_data = Vec::with_capacity(size as usize);
+        file.read_to_end(&mut image_data)
+            .with_context(|| format!("Failed to read image from path: {}", path))?;
+        Ok(image_data)
+    }
+
+    // Decode an image file within the configured limits
+    pub fn decode_file(&self, path: &str) -> Result<DynamicImage> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open image from path: {}", path))?;
+        let size = file
+            .metadata()
+            .with_context(|| format!("Failed to read metadata for: {}", path))?
+            .len();
+        self.check_size(size)?;
+
+        self.decode(BufReader::new(file))
+            .with_context(|| format!("Failed to decode image from path: {}", path))
+    }
+
+    // Decode encoded image bytes within the configured limits
+    pub fn decode_bytes(&self, image_data: &[u8]) -> Result<DynamicImage> {
+        self.check_size(image_data.len() as u64)?;
+
+        self.decode(Cursor::new(image_data))
+            .context("Failed to load image from memory")
+    }
+
+    // Decode a base64 payload (optionally a data URL) into encoded image bytes
+    pub fn decode_base64(&self, base64_data: &str) -> Result<Vec<u8>> {
+        // Strip potential data URL prefix
+        let base64_str = if base64_data.contains("base64,") {
+            base64_data.split("base64,").nth(1).unwrap_or(base64_data)
+        } else {
+            base64_data
+        };
+
+        // Reject oversized payloads before allocating the decoded buffer
+        self.check_size(base64_str.len() as u64 / 4 * 3)?;
+
+        general_purpose::STANDARD
+            .decode(base64_str)
+            .context("Failed to decode base64 image data")
+    }
+
+    // Decode an encoded image without materializing more pixels than needed.
+    // JPEGs are DCT-scaled during decode down towards the model input size;
+    // other formats are checked against the pixel cap from their header first.
+    fn decode<R: BufRead + Seek>(&self, reader: R) -> Result<DynamicImage> {
+        let reader = ImageReader::new(reader)
+            .with_guessed_format()
+            .context("Failed to read image header")?;
+
+        let format = reader
+            .format()
+            .ok_or_else(|| anyhow::anyhow!("Unsupported image format"))?;
+        if !self.limits.allowed_formats.contains(&format) {
+            return Err(ImageInputError::FormatNotAllowed(format!("{:?}", format)).into());
+        }
+        let mut inner = reader.into_inner();
+
+        if format == ImageFormat::Jpeg {
+            let mut decoder = JpegDecoder::new(inner).context("Failed to read JPEG header")?;
+
+            let (width, height) = decoder.dimensions();
+            self.check_bounds(width, height)?;
+
+            // Picks the smallest 1/8..1 scale that still covers the requested size
+            decoder
+                .scale(
+                    self.target_width.min(u16::MAX as u32) as u16,
+                    self.target_height.min(u16::MAX as u32) as u16,
+                )
+                .context("Failed to scale JPEG decode")?;
+
+            let (scaled_width, scaled_height) = decoder.dimensions();
+            debug!(
+                "Decoding JPEG {}x{} at {}x{}",
+                width, height, scaled_width, scaled_height
+            );
+            self.check_pixels(scaled_width, scaled_height)?;
+
+            decoder
+                .set_limits(self.decoder_limits())
+                .context("Image exceeds decoder limits")?;
+            return DynamicImage::from_decoder(decoder).context("Failed to decode JPEG image");
+        }
+
+        // Read the header only, then rewind for the real decode
+        let start = inner.stream_position()?;
+        let (width, height) = ImageReader::with_format(&mut inner, format)
+            .into_dimensions()
+            .context("Failed to read image dimensions")?;
+        self.check_bounds(width, height)?;
+        self.check_pixels(width, height)?;
+        inner.seek(SeekFrom::Start(start))?;
+
+        // Headers can lie about their size, so the decoder enforces the limits too
+        let mut reader = ImageReader::with_format(inner, format);
+        reader.limits(self.decoder_limits());
+        reader.decode().context("Failed to decode image")
+    }
+
+    // Allocation limits handed to the decoders as decompression-bomb protection
+    fn decoder_limits(&self) -> Limits {
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(self.limits.max_width);
+        limits.max_image_height = Some(self.limits.max_height);
+        // Room for the largest allowed image at 16 bits per RGBA channel
+        limits.max_alloc = Some(self.limits.max_decoded_pixels.saturating_mul(8));
+        limits
+    }
+
+    fn check_size(&self, size: u64) -> Result<()> {
+        if size > self.limits.max_bytes {
+            return Err(ImageInputError::TooManyBytes {
+                size,
+                max_bytes: self.limits.max_bytes,
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    // Reject empty images and dimensions beyond the configured bounds
+    fn check_bounds(&self, width: u32, height: u32) -> Result<()> {
+        if width == 0 || height == 0 {
+            return Err(ImageInputError::Empty { width, height }.into());
+        }
+
+        if width > self.limits.max_width || height > self.limits.max_height {
+            return Err(ImageInputError::DimensionsTooLarge {
+                width,
+                height,
+                max_width: self.limits.max_width,
+                max_height: self.limits.max_height,
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    fn check_pixels(&self, width: u32, height: u32) -> Result<()> {
+        if width as u64 * height as u64 > self.limits.max_decoded_pixels {
+            return Err(ImageInputError::TooLarge {
+                width,
+                height,
+                max_pixels: self.limits.max_decoded_pixels,
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    // Process camera frame data
+    pub fn process_camera_frame(
+        &self,
+        width: u32,
+        height: u32,
+        rgba_data: Vec<u8>,
+    ) -> Result<Vec<f32>> {
+        self.check_bounds(width, height)?;
+        self.check_pixels(width, height)?;
+
+        let expected = width as u64 * height as u64 * 4;
+        if rgba_data.len() as u64 != expected {
+            return Err(ImageInputError::InvalidFrame {
+                width,
+                height,
+                expected,
+                actual: rgba_data.len() as u64,
+            }
+            .into());
+        }
+
+        // Create an image buffer from raw RGBA data
+        let img_buffer: ImageBuffer<Rgba<u8>, Vec<u8>> =
+            ImageBuffer::from_raw(width, height, rgba_data)
+                .context("Failed to create image buffer from camera frame")?;
+
+        // Convert to DynamicImage for preprocessing
+        let img = DynamicImage::ImageRgba8(img_buffer);
+
+        self.preprocess_image(&img)
+    }
+
+    // Preprocess image for model input
+    pub fn preprocess_image(&self, img: &DynamicImage) -> Result<Vec<f32>> {
+        // Resize image to target dimensions
+        let resized = img.resize_exact(
+            self.target_width,
+            self.target_height,
+            image::imageops::FilterType::Triangle,
+        );
+
+        // Convert to RGB
+        let rgb_img = resized.to_rgb8();
+
+        // For tract, we need to normalize pixel values typically to [-1, 1] or [0, 1]
+        // and store in HWC format (height, width, channels)
+        let mut normalized_data =
+            Vec::with_capacity((self.target_width * self.target_height * 3) as usize);
+
+        for pixel in rgb_img.pixels() {
+            // Normalize to [0, 1] range
+            normalized_data.push(pixel[0] as f32 / 255.0);
+            normalized_data.push(pixel[1] as f32 / 255.0);
+            normalized_data.push(pixel[2] as f32 / 255.0);
+        }
+
+        Ok(normalized_data)
+    }
+
+    // Identifies what preprocess_image produces, so cached results are only
+    // reused for the same model input. Bump the version when the pipeline changes.
+    pub fn profile(&self) -> String {
+        format!(
+            "v1-rgb-unit-hwc-{}x{}-triangle",
+            self.target_width, self.target_height
+        )
+    }
+
+    // Cap the number of pixels a single decode may produce
+    pub fn set_max_decoded_pixels(&mut self, max_pixels: u64) {
+        self.limits.max_decoded_pixels = max_pixels;
+    }
+
+    pub fn set_limits(&mut self, limits: InputLimits) {
+        self.limits = limits;
+    }
+
+    pub fn limits(&self) -> &InputLimits {
+        &self.limits
+    }
+
+    // Set custom target dimensions if needed
+    pub fn set_target_dimensions(&mut self, width: u32, height: u32) {
+        self.target_width = width;
+        self.target_height = height;
+    }
+}
+
+// Encode a small JPEG preview of a decoded image, keeping its aspect ratio
+pub fn thumbnail_jpeg(img: &DynamicImage, max_side: u32) -> Result<Vec<u8>> {
+    // The JPEG encoder has no alpha channel
+    let thumbnail = DynamicImage::ImageRgb8(img.thumbnail(max_side, max_side).to_rgb8());
+
+    let mut bytes = Cursor::new(Vec::new());
+    thumbnail
+        .write_to(&mut bytes, ImageOutputFormat::Jpeg(80))
+        .context("Failed to encode thumbnail")?;
+
+    Ok(bytes.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 24-bit BMP header declaring the given size, with no pixel data
+    fn bmp_header(width: u32, height: u32) -> Vec<u8> {
+        let mut header = Vec::with_capacity(54);
+        header.extend_from_slice(b"BM");
+        header.extend_from_slice(&54u32.to_le_bytes()); // file size
+        header.extend_from_slice(&0u32.to_le_bytes()); // reserved
+        header.extend_from_slice(&54u32.to_le_bytes()); // pixel data offset
+        header.extend_from_slice(&40u32.to_le_bytes()); // BITMAPINFOHEADER
+        header.extend_from_slice(&(width as i32).to_le_bytes());
+        header.extend_from_slice(&(height as i32).to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes()); // planes
+        header.extend_from_slice(&24u16.to_le_bytes()); // bits per pixel
+        header.extend_from_slice(&[0; 24]); // no compression, default palette
+        header
+    }
+
+    fn input_error(result: Result<impl std::fmt::Debug>) -> ImageInputError {
+        let error = result.expect_err("input was accepted");
+        match error.downcast::<ImageInputError>() {
+            Ok(error) => error,
+            Err(error) => panic!("unexpected error: {:#}", error),
+        }
+    }
+
+    #[test]
+    fn rejects_camera_frame_of_wrong_length() {
+        let processor = ImageProcessor::new();
+
+        let error = input_error(processor.process_camera_frame(4, 2, vec![0; 4 * 2 * 4 - 1]));
+        assert!(matches!(
+            error,
+            ImageInputError::InvalidFrame {
+                width: 4,
+                height: 2,
+                expected: 32,
+                actual: 31,
+            }
+        ));
+    }
+
+    #[test]
+    fn rejects_header_beyond_dimension_limits() {
+        let processor = ImageProcessor::new();
+
+        let error = input_error(processor.decode_bytes(&bmp_header(20_000, 10)));
+        assert!(matches!(
+            error,
+            ImageInputError::DimensionsTooLarge {
+                width: 20_000,
+                height: 10,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn rejects_header_beyond_pixel_limit() {
+        let processor = ImageProcessor::new();
+
+        // Within the per-side bounds, but 100 MP exceeds the default 24 MP cap
+        let error = input_error(processor.decode_bytes(&bmp_header(10_000, 10_000)));
+        assert!(matches!(
+            error,
+            ImageInputError::TooLarge {
+                width: 10_000,
+                height: 10_000,
+                max_pixels: DEFAULT_MAX_DECODED_PIXELS,
+            }
+        ));
+    }
+
+    #[test]
+    fn rejects_disallowed_format() {
+        let mut png = Cursor::new(Vec::new());
+        DynamicImage::new_rgb8(2, 2)
+            .write_to(&mut png, ImageOutputFormat::Png)
+            .unwrap();
+
+        let mut processor = ImageProcessor::new();
+        processor.set_limits(InputLimits {
+            allowed_formats: vec![ImageFormat::Jpeg],
+            ..InputLimits::default()
+        });
+
+        let error = input_error(processor.decode_bytes(png.get_ref()));
+        assert!(matches!(error, ImageInputError::FormatNotAllowed(ref format) if format == "Png"));
+    }
+}
@@ -0,0 +1,41 @@
+// Recognition engine shared by the Tauri app and the headless tools:
+// preprocessing, model loading, inference, postprocessing and labels
+pub mod benchmark;
+pub mod calibration;
+pub mod csv;
+pub mod ensemble;
+pub mod hash;
+pub mod image_processor;
+pub mod model_cache;
+pub mod model_compare;
+pub mod model_format;
+pub mod model_inspect;
+pub mod model_integrity;
+pub mod model_manager;
+pub mod model_package;
+pub mod organize;
+pub mod result_cache;
+pub mod scan;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod tagging;
+#[cfg(test)]
+mod test_models;
+
+pub use ensemble::EnsembleConfig;
+pub use image::DynamicImage;
+pub use image_processor::{thumbnail_jpeg, ImageInputError, ImageProcessor, InputLimits};
+pub use model_cache::ModelCache;
+pub use model_format::{ModelFormat, ModelPrecision};
+pub use model_integrity::TrustedKeys;
+pub use model_manager::{ModelError, ModelHandle, ModelInfo, ModelManager, DEFAULT_TOP_K};
+pub use model_package::{ModelManifest, ModelPackage};
+pub use result_cache::{CacheKey, CacheStats, ResultCache};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecognitionResult {
+    pub label: String,
+    pub confidence: f32,
+}
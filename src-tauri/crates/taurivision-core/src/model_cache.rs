@@ -0,0 +1,236 @@
+// On-disk cache of prepared models in tract's NNEF format. Parsing ONNX or
+// TFLite, type analysis and decluttering dominate load times on phones;
+// a cached model only needs the final optimization pass.
+use crate::hash::to_hex;
+use crate::model_format::{ModelFormat, ModelPrecision};
+use anyhow::{Context, Result};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use tract_onnx::prelude::*;
+use tract_onnx_opl::WithOnnx;
+
+// tract-core version from Cargo.lock, see build.rs. NNEF written by one
+// release is not guaranteed to load in another.
+const TRACT_VERSION: &str = env!("TAURIVISION_TRACT_VERSION");
+
+// Bumped whenever what goes into an entry changes
+const ENTRY_VERSION: &str = "1";
+
+// Written after the model, so a complete entry always has both files
+#[derive(Serialize, Deserialize, Debug)]
+struct EntryMetadata {
+    format: ModelFormat,
+    precision: ModelPrecision,
+}
+
+#[derive(Clone)]
+pub struct ModelCache {
+    dir: PathBuf,
+}
+
+impl ModelCache {
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create model cache dir: {:?}", dir))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    // Entries are tied to the exact model file, tract release and input shape
+    pub fn key(model_sha256: &str, format: ModelFormat, input_shape: &[usize]) -> String {
+        let mut hasher = Sha256::new();
+        let format = format!("{:?}", format);
+        let shape = format!("{:?}", input_shape);
+        for part in [
+            model_sha256,
+            format.as_str(),
+            shape.as_str(),
+            TRACT_VERSION,
+            ENTRY_VERSION,
+        ] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        to_hex(&hasher.finalize())
+    }
+
+    // None on a miss; unreadable entries are removed so they get rebuilt
+    pub fn load(&self, key: &str, format: ModelFormat) -> Option<(TypedModel, ModelPrecision)> {
+        let metadata_path = self.metadata_path(key);
+        if !metadata_path.exists() {
+            return None;
+        }
+
+        match self.read_entry(key, format) {
+            Ok(entry) => {
+                info!("Loaded model from cache {:?}", self.model_path(key));
+                Some(entry)
+            }
+            Err(e) => {
+                debug!("Discarding unreadable model cache entry {}: {:#}", key, e);
+                self.remove(key);
+                None
+            }
+        }
+    }
+
+    pub fn store(
+        &self,
+        key: &str,
+        model: &TypedModel,
+        format: ModelFormat,
+        precision: ModelPrecision,
+    ) -> Result<()> {
+        let model_path = self.model_path(key);
+        let tmp_path = model_path.with_extension("tar.tmp");
+
+        let file =
+            File::create(&tmp_path).with_context(|| format!("Failed to create {:?}", tmp_path))?;
+        nnef()
+            .write_to_tar(model, file)
+            .with_context(|| format!("Failed to serialize model to {:?}", tmp_path))?;
+        fs::rename(&tmp_path, &model_path)
+            .with_context(|| format!("Failed to write {:?}", model_path))?;
+
+        let metadata = serde_json::to_vec(&EntryMetadata { format, precision })?;
+        let metadata_path = self.metadata_path(key);
+        let tmp_path = metadata_path.with_extension("json.tmp");
+        fs::write(&tmp_path, metadata)
+            .with_context(|| format!("Failed to write {:?}", tmp_path))?;
+        fs::rename(&tmp_path, &metadata_path)
+            .with_context(|| format!("Failed to write {:?}", metadata_path))?;
+
+        info!("Cached prepared model at {:?}", model_path);
+        Ok(())
+    }
+
+    pub fn remove(&self, key: &str) {
+        let _ = fs::remove_file(self.metadata_path(key));
+        let _ = fs::remove_file(self.model_path(key));
+    }
+
+    fn read_entry(&self, key: &str, format: ModelFormat) -> Result<(TypedModel, ModelPrecision)> {
+        let metadata: EntryMetadata = serde_json::from_slice(&fs::read(self.metadata_path(key))?)
+            .context("Invalid entry metadata")?;
+        if metadata.format != format {
+            anyhow::bail!("Entry was written for a {:?} model", metadata.format);
+        }
+
+        let file = File::open(self.model_path(key))?;
+        let model = nnef()
+            .model_for_read(&mut BufReader::new(file))
+            .context("Invalid NNEF model")?;
+
+        Ok((model, metadata.precision))
+    }
+
+    fn model_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.nnef.tar", key))
+    }
+
+    fn metadata_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+// NNEF with tract's extensions, which cover the operators both formats decompose into
+fn nnef() -> tract_nnef::framework::Nnef {
+    tract_nnef::nnef().with_tract_core().with_onnx()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_format::{decluttered_model, LoadedModel};
+    use crate::model_manager::INPUT_SIZE;
+    use crate::test_models::float_model;
+
+    fn temp_cache(name: &str) -> ModelCache {
+        let dir = std::env::temp_dir().join(format!(
+            "taurivision-model-cache-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        ModelCache::open(&dir).unwrap()
+    }
+
+    fn key() -> String {
+        ModelCache::key("abc", ModelFormat::Onnx, &[1, 3, INPUT_SIZE, INPUT_SIZE])
+    }
+
+    #[test]
+    fn keys_use_the_resolved_tract_version() {
+        let parts: Vec<&str> = TRACT_VERSION.split('.').collect();
+        assert_eq!(parts.len(), 3, "{}", TRACT_VERSION);
+        assert!(parts.iter().all(|part| part.parse::<u32>().is_ok()));
+
+        assert_ne!(
+            key(),
+            ModelCache::key("abd", ModelFormat::Onnx, &[1, 3, 224, 224])
+        );
+        assert_ne!(
+            key(),
+            ModelCache::key("abc", ModelFormat::Tflite, &[1, 3, 224, 224])
+        );
+        assert_ne!(
+            key(),
+            ModelCache::key("abc", ModelFormat::Onnx, &[2, 3, 224, 224])
+        );
+    }
+
+    #[test]
+    fn stores_and_loads_prepared_models() {
+        let cache = temp_cache("round-trip");
+        let (model, precision) = decluttered_model(&float_model(), ModelFormat::Onnx, 1).unwrap();
+
+        assert!(cache.load(&key(), ModelFormat::Onnx).is_none());
+        cache
+            .store(&key(), &model, ModelFormat::Onnx, precision)
+            .unwrap();
+
+        let (cached, cached_precision) = cache.load(&key(), ModelFormat::Onnx).unwrap();
+        assert_eq!(cached_precision, ModelPrecision::Float);
+        assert_eq!(cached.input_fact(0).unwrap(), model.input_fact(0).unwrap());
+        assert_eq!(
+            cached.output_fact(0).unwrap(),
+            model.output_fact(0).unwrap()
+        );
+
+        // An entry for another format is not reused
+        assert!(cache.load(&key(), ModelFormat::Tflite).is_none());
+        assert!(!cache.metadata_path(&key()).exists());
+
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt_entries_fall_back_to_the_model_file() {
+        let cache = temp_cache("corrupt");
+        let model_bytes = float_model();
+        let image = vec![0.25f32; INPUT_SIZE * INPUT_SIZE * 3];
+
+        let first =
+            LoadedModel::load_cached(&model_bytes, "abc", ModelFormat::Onnx, 1, &cache).unwrap();
+        let expected = first.run(&[&image]).unwrap();
+        assert!(cache.model_path(&key()).exists());
+
+        fs::write(cache.model_path(&key()), b"not a tar file").unwrap();
+        assert!(cache.load(&key(), ModelFormat::Onnx).is_none());
+        assert!(!cache.model_path(&key()).exists());
+
+        // A corrupt entry is rebuilt from the model file and written again
+        fs::write(cache.metadata_path(&key()), b"{}").unwrap();
+        let rebuilt =
+            LoadedModel::load_cached(&model_bytes, "abc", ModelFormat::Onnx, 1, &cache).unwrap();
+        assert_eq!(rebuilt.run(&[&image]).unwrap(), expected);
+        assert!(cache.load(&key(), ModelFormat::Onnx).is_some());
+
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+}
@@ -0,0 +1,292 @@
+// A/B comparison of two registered models on the same images, for deciding
+// whether a candidate model can replace the current one. Unlike
+// `calibration::compare_models` it runs the models as registered, with
+// their labels, normalization and activation.
+use crate::benchmark::{latency_stats, LatencyStats};
+use crate::image_processor::ImageProcessor;
+use crate::model_manager::{ModelHandle, DEFAULT_TOP_K};
+use crate::RecognitionResult;
+use anyhow::{Context, Result};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct CompareOptions {
+    pub top_k: usize,
+    // Descend into subfolders of the given folders
+    pub recursive: bool,
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        Self {
+            top_k: DEFAULT_TOP_K,
+            recursive: false,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ComparedModel {
+    pub id: String,
+    pub sha256: String,
+    pub latency: LatencyStats,
+}
+
+// One image the models disagree on, with both top-k lists side by side
+#[derive(Serialize, Debug, Clone)]
+pub struct ImageDisagreement {
+    pub path: String,
+    pub a: Vec<RecognitionResult>,
+    pub b: Vec<RecognitionResult>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct AbReport {
+    pub images: usize,
+    pub failed: Vec<String>,
+    pub top_k: usize,
+    pub model_a: ComparedModel,
+    pub model_b: ComparedModel,
+    // Share of images where both models agree on the top-1 label
+    pub agreement_rate: f64,
+    // Share of images where each model's top-1 label is among the other's top-k
+    pub top1_overlap: f64,
+    // Mean share of top-k labels the models have in common
+    pub top_k_overlap: f64,
+    // B's p50 latency minus A's; negative when B is faster
+    pub latency_diff_ms: f64,
+    pub disagreements: Vec<ImageDisagreement>,
+}
+
+impl AbReport {
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize comparison report")
+    }
+
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_json()?)
+            .with_context(|| format!("Failed to write comparison report to {:?}", path))
+    }
+}
+
+// Run both models on every image. Images that cannot be read are listed as
+// failed instead of aborting the comparison.
+pub fn compare(
+    model_a: &ModelHandle,
+    model_b: &ModelHandle,
+    image_paths: &[PathBuf],
+    image_processor: &ImageProcessor,
+    options: &CompareOptions,
+) -> Result<AbReport> {
+    let top_k = options.top_k;
+    if top_k == 0 {
+        anyhow::bail!("top_k must be at least 1");
+    }
+    if model_a.info().id == model_b.info().id {
+        anyhow::bail!("Cannot compare model {} with itself", model_a.info().id);
+    }
+
+    let mut times_a = Vec::with_capacity(image_paths.len());
+    let mut times_b = Vec::with_capacity(image_paths.len());
+    let mut failed = Vec::new();
+    let mut disagreements = Vec::new();
+    let mut agreements = 0;
+    let mut top1_overlaps = 0;
+    let mut overlap_sum = 0.0;
+
+    for path in image_paths {
+        let data = match image_processor.load_image(&path.to_string_lossy()) {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("Skipping {:?}: {:#}", path, e);
+                failed.push(path.to_string_lossy().into_owned());
+                continue;
+            }
+        };
+
+        let (top_a, time_a) = recognize_timed(model_a, &data, top_k)?;
+        let (top_b, time_b) = recognize_timed(model_b, &data, top_k)?;
+        times_a.push(time_a);
+        times_b.push(time_b);
+
+        let score = score_image(&top_a, &top_b, top_k);
+        overlap_sum += score.top_k_overlap;
+        if score.top1_overlap {
+            top1_overlaps += 1;
+        }
+        if score.agrees {
+            agreements += 1;
+        } else {
+            disagreements.push(ImageDisagreement {
+                path: path.to_string_lossy().into_owned(),
+                a: recognition_results(top_a),
+                b: recognition_results(top_b),
+            });
+        }
+    }
+
+    let images = times_a.len();
+    if images == 0 {
+        anyhow::bail!("None of the {} image(s) could be read", image_paths.len());
+    }
+
+    let model_a = compared_model(model_a, &mut times_a);
+    let model_b = compared_model(model_b, &mut times_b);
+
+    Ok(AbReport {
+        images,
+        failed,
+        top_k,
+        agreement_rate: agreements as f64 / images as f64,
+        top1_overlap: top1_overlaps as f64 / images as f64,
+        top_k_overlap: overlap_sum / images as f64,
+        latency_diff_ms: model_b.latency.p50_ms - model_a.latency.p50_ms,
+        model_a,
+        model_b,
+        disagreements,
+    })
+}
+
+// How the top-k lists of both models compare on one image
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ImageScore {
+    // Share of the top-k labels both lists contain
+    top_k_overlap: f64,
+    // Each top-1 label is in the other list
+    top1_overlap: bool,
+    // Same top-1 label
+    agrees: bool,
+}
+
+fn score_image(top_a: &[(String, f32)], top_b: &[(String, f32)], top_k: usize) -> ImageScore {
+    let contains = |results: &[(String, f32)], label: &str| results.iter().any(|(l, _)| l == label);
+
+    let shared = top_a
+        .iter()
+        .filter(|(label, _)| contains(top_b, label))
+        .count();
+    let top1_a = top_a.first().map(|(label, _)| label.as_str());
+    let top1_b = top_b.first().map(|(label, _)| label.as_str());
+
+    ImageScore {
+        top_k_overlap: shared as f64 / top_k as f64,
+        top1_overlap: top1_a.is_some_and(|label| contains(top_b, label))
+            && top1_b.is_some_and(|label| contains(top_a, label)),
+        agrees: top1_a == top1_b,
+    }
+}
+
+fn recognize_timed(
+    model: &ModelHandle,
+    data: &[f32],
+    top_k: usize,
+) -> Result<(Vec<(String, f32)>, Duration)> {
+    let start = Instant::now();
+    let results = model
+        .recognize(data, top_k)
+        .with_context(|| format!("Inference with {} failed", model.info().id))?;
+    Ok((results, start.elapsed()))
+}
+
+fn compared_model(model: &ModelHandle, times: &mut [Duration]) -> ComparedModel {
+    ComparedModel {
+        id: model.info().id.clone(),
+        sha256: model.info().sha256.clone(),
+        latency: latency_stats(times),
+    }
+}
+
+fn recognition_results(results: Vec<(String, f32)>) -> Vec<RecognitionResult> {
+    results
+        .into_iter()
+        .map(|(label, confidence)| RecognitionResult { label, confidence })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn top(labels: &[&str]) -> Vec<(String, f32)> {
+        labels
+            .iter()
+            .enumerate()
+            .map(|(rank, label)| (label.to_string(), 1.0 / (rank + 1) as f32))
+            .collect()
+    }
+
+    fn score(a: &[&str], b: &[&str], top_k: usize) -> ImageScore {
+        score_image(&top(a), &top(b), top_k)
+    }
+
+    #[test]
+    fn identical_lists_agree_fully() {
+        let expected = ImageScore {
+            top_k_overlap: 1.0,
+            top1_overlap: true,
+            agrees: true,
+        };
+        assert_eq!(
+            score(&["cat", "dog", "fox"], &["cat", "dog", "fox"], 3),
+            expected
+        );
+    }
+
+    #[test]
+    fn reordered_lists_overlap_without_agreeing() {
+        let expected = ImageScore {
+            top_k_overlap: 1.0,
+            top1_overlap: true,
+            agrees: false,
+        };
+        assert_eq!(
+            score(&["cat", "dog", "fox"], &["dog", "fox", "cat"], 3),
+            expected
+        );
+    }
+
+    #[test]
+    fn top1_overlap_needs_both_top_labels_in_the_other_list() {
+        // B's top-1 "owl" is not among A's labels
+        let expected = ImageScore {
+            top_k_overlap: 2.0 / 3.0,
+            top1_overlap: false,
+            agrees: false,
+        };
+        assert_eq!(
+            score(&["cat", "dog", "fox"], &["owl", "cat", "dog"], 3),
+            expected
+        );
+    }
+
+    #[test]
+    fn disjoint_lists_share_nothing() {
+        let expected = ImageScore {
+            top_k_overlap: 0.0,
+            top1_overlap: false,
+            agrees: false,
+        };
+        assert_eq!(score(&["cat", "dog"], &["owl", "eel"], 2), expected);
+    }
+
+    #[test]
+    fn short_lists_count_against_top_k() {
+        // A model with fewer labels than k still scores against k
+        let scored = score(&["cat"], &["cat", "dog"], 4);
+        assert_eq!(scored.top_k_overlap, 0.25);
+        assert!(scored.top1_overlap && scored.agrees);
+
+        // Neither model returning anything counts as agreeing on no label
+        let expected = ImageScore {
+            top_k_overlap: 0.0,
+            top1_overlap: false,
+            agrees: true,
+        };
+        assert_eq!(score(&[], &[], 3), expected);
+    }
+}
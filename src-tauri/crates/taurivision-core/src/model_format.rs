@@ -0,0 +1,434 @@
+// Model file formats. Each format turns a file into a runnable tract plan;
+// preprocessing before and postprocessing after the plan are shared.
+use crate::model_cache::ModelCache;
+use crate::model_manager::{ModelError, INPUT_SIZE};
+use anyhow::{Context, Result};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use std::path::Path;
+use tract_onnx::prelude::*;
+
+pub type TractModel =
+    RunnableModel<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelFormat {
+    Onnx,
+    Tflite,
+}
+
+// FlatBuffers file identifier of TFLite models, stored at offset 4
+const TFLITE_IDENTIFIER: &[u8] = b"TFL3";
+
+impl ModelFormat {
+    // ONNX files have no magic bytes, so anything not recognized as TFLite
+    // by its identifier or extension is treated as ONNX
+    pub fn detect(path: Option<&Path>, model_bytes: &[u8]) -> Self {
+        if model_bytes.get(4..8) == Some(TFLITE_IDENTIFIER) {
+            return ModelFormat::Tflite;
+        }
+
+        let extension = path
+            .and_then(|path| path.extension())
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("tflite") => ModelFormat::Tflite,
+            _ => ModelFormat::Onnx,
+        }
+    }
+
+    // How the model expects its input tensor laid out
+    pub fn input_layout(self) -> InputLayout {
+        match self {
+            ModelFormat::Onnx => InputLayout::Nchw,
+            ModelFormat::Tflite => InputLayout::Nhwc,
+        }
+    }
+
+    // ONNX models get their batch size from us, TFLite files fix it
+    pub fn can_rebatch(self) -> bool {
+        self == ModelFormat::Onnx
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum InputLayout {
+    Nchw,
+    Nhwc,
+}
+
+// Numeric precision of a model's weights and activations
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelPrecision {
+    Float,
+    // QDQ (QuantizeLinear/DequantizeLinear pairs), QLinear* operators or
+    // quantized TFLite tensors
+    Int8,
+}
+
+// A model ready for inference with a fixed batch size
+pub struct LoadedModel {
+    plan: TractModel,
+    pub format: ModelFormat,
+    pub precision: ModelPrecision,
+    pub batch_size: usize,
+}
+
+impl LoadedModel {
+    pub fn load(model_bytes: &[u8], format: ModelFormat, batch_size: usize) -> Result<Self> {
+        let (model, precision) = decluttered_model(model_bytes, format, batch_size)?;
+        Self::from_decluttered(model, format, precision, batch_size)
+    }
+
+    // Like `load`, but reuse the prepared model from `cache` when there is one
+    // and fill the cache otherwise
+    pub fn load_cached(
+        model_bytes: &[u8],
+        model_sha256: &str,
+        format: ModelFormat,
+        batch_size: usize,
+        cache: &ModelCache,
+    ) -> Result<Self> {
+        let key = ModelCache::key(model_sha256, format, &input_shape(format, batch_size));
+
+        if let Some((model, precision)) = cache.load(&key, format) {
+            match Self::from_decluttered(model, format, precision, batch_size) {
+                Ok(model) => return Ok(model),
+                Err(e) => {
+                    warn!("Cached model is unusable, rebuilding it: {:#}", e);
+                    cache.remove(&key);
+                }
+            }
+        }
+
+        let (model, precision) = decluttered_model(model_bytes, format, batch_size)?;
+        // A failed write only costs the next start its speedup
+        if let Err(e) = cache.store(&key, &model, format, precision) {
+            warn!("Failed to cache model: {:#}", e);
+        }
+        Self::from_decluttered(model, format, precision, batch_size)
+    }
+
+    fn from_decluttered(
+        model: TypedModel,
+        format: ModelFormat,
+        precision: ModelPrecision,
+        batch_size: usize,
+    ) -> Result<Self> {
+        let plan = model
+            .into_optimized()
+            .context("Failed to optimize model")?
+            .into_runnable()
+            .context("Failed to convert model to runnable")?;
+
+        let model = Self {
+            plan,
+            format,
+            precision,
+            batch_size,
+        };
+        if precision == ModelPrecision::Int8 {
+            model.verify_quantized()?;
+        }
+
+        Ok(model)
+    }
+
+    pub fn input_shape(&self) -> Vec<usize> {
+        input_shape(self.format, self.batch_size)
+    }
+
+    // Run a batch of preprocessed HWC images, returning the scores of each image
+    pub fn run(&self, images: &[&[f32]]) -> Result<Vec<Vec<f32>>> {
+        if images.len() != self.batch_size {
+            return Err(ModelError::InferenceError(format!(
+                "Model expects batches of {} image(s), got {}",
+                self.batch_size,
+                images.len()
+            ))
+            .into());
+        }
+
+        let input = images_to_tensor(images, self.format.input_layout());
+        let outputs = self
+            .plan
+            .run(tvec!(input.into()))
+            .map_err(|e| ModelError::InferenceError(e.to_string()))?;
+        let scores = output_scores(&outputs[0])?;
+
+        let per_image = scores.len() / images.len();
+        Ok(scores
+            .chunks(per_image.max(1))
+            .map(|chunk| chunk.to_vec())
+            .collect())
+    }
+
+    // Broken scales or zero points in a quantized model tend to show up as
+    // non-finite or constant scores, so catch them at load time on gray images
+    fn verify_quantized(&self) -> Result<()> {
+        let gray = vec![0.5f32; INPUT_SIZE * INPUT_SIZE * 3];
+        let images = vec![gray.as_slice(); self.batch_size];
+        let scores = self
+            .run(&images)
+            .context("Quantized model failed a test inference")?;
+        check_quantized_scores(scores.first().map(Vec::as_slice).unwrap_or_default())
+    }
+}
+
+fn check_quantized_scores(scores: &[f32]) -> Result<()> {
+    if scores.iter().any(|score| !score.is_finite()) {
+        return Err(ModelError::LoadError(
+            "Quantized model produced non-finite scores".to_string(),
+        )
+        .into());
+    }
+    if scores.windows(2).all(|pair| pair[0] == pair[1]) {
+        warn!("Quantized model produced identical scores for every class");
+    }
+
+    Ok(())
+}
+
+// Parse a model file into a typed, decluttered graph, the form that is cached
+pub(crate) fn decluttered_model(
+    model_bytes: &[u8],
+    format: ModelFormat,
+    batch_size: usize,
+) -> Result<(TypedModel, ModelPrecision)> {
+    match format {
+        ModelFormat::Onnx => load_onnx(model_bytes, batch_size),
+        ModelFormat::Tflite => load_tflite(model_bytes, batch_size),
+    }
+}
+
+fn input_shape(format: ModelFormat, batch_size: usize) -> Vec<usize> {
+    match format.input_layout() {
+        InputLayout::Nchw => vec![batch_size, 3, INPUT_SIZE, INPUT_SIZE],
+        InputLayout::Nhwc => vec![batch_size, INPUT_SIZE, INPUT_SIZE, 3],
+    }
+}
+
+// Standard ONNX operators that only appear in quantized graphs
+const QUANTIZED_OPS: &[&str] = &[
+    "QuantizeLinear",
+    "DequantizeLinear",
+    "DynamicQuantizeLinear",
+    "QLinearConv",
+    "QLinearMatMul",
+    "ConvInteger",
+    "MatMulInteger",
+];
+
+// Domain of ONNX Runtime's contrib operators, e.g. QLinearAdd
+const ONNX_RUNTIME_DOMAIN: &str = "com.microsoft";
+
+fn load_onnx(model_bytes: &[u8], batch_size: usize) -> Result<(TypedModel, ModelPrecision)> {
+    let onnx = tract_onnx::onnx();
+    let proto = onnx
+        .proto_model_for_read(&mut Cursor::new(model_bytes))
+        .context("Failed to load ONNX model")?;
+    let precision = onnx_precision(&proto)?;
+
+    let model = onnx
+        .model_for_proto_model(&proto)
+        .context("Failed to load ONNX model")?
+        // Specify the input shape (batch, 3 channels, 224 height, 224 width)
+        .with_input_fact(
+            0,
+            InferenceFact::dt_shape(
+                f32::datum_type(),
+                tvec!(batch_size, 3, INPUT_SIZE, INPUT_SIZE),
+            ),
+        )
+        .context("Failed to set input shape")?
+        .into_typed()
+        .context("Failed to analyse model")?
+        .into_decluttered()
+        .context("Failed to declutter model")?;
+
+    Ok((model, precision))
+}
+
+// Detect quantization, and reject operators tract has no implementation for
+fn onnx_precision(proto: &tract_onnx::pb::ModelProto) -> Result<ModelPrecision> {
+    let nodes = proto
+        .graph
+        .as_ref()
+        .map(|graph| graph.node.as_slice())
+        .unwrap_or_default();
+
+    let mut contrib_ops: Vec<&str> = nodes
+        .iter()
+        .filter(|node| node.domain == ONNX_RUNTIME_DOMAIN)
+        .map(|node| node.op_type.as_str())
+        .collect();
+    if !contrib_ops.is_empty() {
+        contrib_ops.sort_unstable();
+        contrib_ops.dedup();
+        return Err(ModelError::LoadError(format!(
+            "Model uses ONNX Runtime contrib operators ({}) that tract cannot run; \
+             quantize to the QDQ format or with standard QLinear operators only",
+            contrib_ops.join(", ")
+        ))
+        .into());
+    }
+
+    let quantized = nodes
+        .iter()
+        .any(|node| QUANTIZED_OPS.contains(&node.op_type.as_str()));
+    Ok(if quantized {
+        ModelPrecision::Int8
+    } else {
+        ModelPrecision::Float
+    })
+}
+
+#[cfg(feature = "tflite")]
+fn load_tflite(model_bytes: &[u8], batch_size: usize) -> Result<(TypedModel, ModelPrecision)> {
+    let model = tract_tflite::tflite()
+        .model_for_read(&mut Cursor::new(model_bytes))
+        .context("Failed to load TFLite model")?;
+
+    // TFLite graphs come with fixed, already typed input shapes
+    let input = model.input_fact(0).context("TFLite model has no input")?;
+    if input.datum_type != f32::datum_type() {
+        return Err(ModelError::LoadError(format!(
+            "TFLite model takes {:?} input, only float inputs are supported",
+            input.datum_type
+        ))
+        .into());
+    }
+    let expected = [batch_size, INPUT_SIZE, INPUT_SIZE, 3];
+    if input.shape.as_concrete() != Some(&expected[..]) {
+        return Err(ModelError::LoadError(format!(
+            "TFLite model input shape is {:?}, expected {:?}",
+            input.shape, expected
+        ))
+        .into());
+    }
+
+    let quantized = model.nodes().iter().any(|node| {
+        node.outputs
+            .iter()
+            .any(|output| output.fact.datum_type.is_quantized())
+    });
+    let precision = if quantized {
+        ModelPrecision::Int8
+    } else {
+        ModelPrecision::Float
+    };
+
+    let model = model
+        .into_decluttered()
+        .context("Failed to declutter model")?;
+
+    Ok((model, precision))
+}
+
+#[cfg(not(feature = "tflite"))]
+fn load_tflite(_model_bytes: &[u8], _batch_size: usize) -> Result<(TypedModel, ModelPrecision)> {
+    Err(ModelError::LoadError("Built without TFLite support".to_string()).into())
+}
+
+// Scores as f32; fully quantized graphs may end in an integer tensor
+fn output_scores(output: &Tensor) -> Result<Vec<f32>> {
+    let scores = output
+        .cast_to::<f32>()
+        .map_err(|e| ModelError::InferenceError(e.to_string()))?;
+    let view = scores
+        .to_array_view::<f32>()
+        .map_err(|e| ModelError::InferenceError(e.to_string()))?;
+    Ok(view.iter().copied().collect())
+}
+
+// Pack preprocessed HWC images into a single input tensor
+fn images_to_tensor(images: &[&[f32]], layout: InputLayout) -> Tensor {
+    match layout {
+        InputLayout::Nchw => tract_ndarray::Array4::from_shape_fn(
+            (images.len(), 3, INPUT_SIZE, INPUT_SIZE),
+            |(n, c, y, x)| images[n][(y * INPUT_SIZE + x) * 3 + c],
+        )
+        .into_tensor(),
+        InputLayout::Nhwc => tract_ndarray::Array4::from_shape_fn(
+            (images.len(), INPUT_SIZE, INPUT_SIZE, 3),
+            |(n, y, x, c)| images[n][(y * INPUT_SIZE + x) * 3 + c],
+        )
+        .into_tensor(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_models::{self, float_model, node, quantized_model};
+
+    // Every pixel has the same red, green and blue values
+    fn solid(rgb: [f32; 3]) -> Vec<f32> {
+        rgb.repeat(INPUT_SIZE * INPUT_SIZE)
+    }
+
+    fn assert_scores(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-4, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn loads_float_and_qdq_onnx_models() {
+        let image = solid([0.6, 0.2, 1.0]);
+
+        let float = LoadedModel::load(&float_model(), ModelFormat::Onnx, 1).unwrap();
+        assert_eq!(float.precision, ModelPrecision::Float);
+        assert_eq!(float.input_shape(), vec![1, 3, INPUT_SIZE, INPUT_SIZE]);
+        assert_scores(&float.run(&[&image]).unwrap()[0], &[0.6, 0.2, 1.0]);
+
+        // Loading also runs the gray image check on the quantized model
+        let quantized = LoadedModel::load(&quantized_model(0.5), ModelFormat::Onnx, 1).unwrap();
+        assert_eq!(quantized.precision, ModelPrecision::Int8);
+        assert_scores(&quantized.run(&[&image]).unwrap()[0], &[0.5, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn quantized_operators_mark_a_model_int8() {
+        let plain = test_models::model(vec![node("Relu", &["input"], &["scores"])], Vec::new());
+        assert_eq!(onnx_precision(&plain).unwrap(), ModelPrecision::Float);
+
+        for op in QUANTIZED_OPS {
+            let nodes = vec![
+                node(op, &["input"], &["q"]),
+                node("Relu", &["q"], &["scores"]),
+            ];
+            let model = test_models::model(nodes, Vec::new());
+            assert_eq!(
+                onnx_precision(&model).unwrap(),
+                ModelPrecision::Int8,
+                "{}",
+                op
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_onnx_runtime_contrib_operators() {
+        let mut add = node("QLinearAdd", &["input", "input"], &["scores"]);
+        add.domain = ONNX_RUNTIME_DOMAIN.to_string();
+        let model = test_models::model(vec![add], Vec::new());
+
+        let error = onnx_precision(&model).unwrap_err().to_string();
+        assert!(error.contains("QLinearAdd"), "{}", error);
+    }
+
+    #[test]
+    fn quantized_scores_must_be_finite() {
+        assert!(check_quantized_scores(&[0.2, 0.8]).is_ok());
+        // Constant scores are suspicious but not always wrong
+        assert!(check_quantized_scores(&[0.5, 0.5]).is_ok());
+        assert!(check_quantized_scores(&[0.2, f32::NAN]).is_err());
+        assert!(check_quantized_scores(&[f32::INFINITY, 0.8]).is_err());
+    }
+}
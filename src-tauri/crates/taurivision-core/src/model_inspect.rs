@@ -0,0 +1,441 @@
+// Static description of a model file, for diagnosing models that fail to
+// load. ONNX graphs are read from the protobuf directly, so the report is
+// complete even when tract cannot build the model.
+use crate::hash::sha256_hex;
+use crate::model_format::{LoadedModel, ModelFormat, ModelPrecision};
+use crate::model_integrity::check_model_bytes;
+use crate::model_package::{is_package, ModelManifest, ModelPackage};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::path::Path;
+use tract_onnx::pb;
+use tract_onnx::prelude::*;
+
+// Standard ONNX operator domains; tract looks operators up by name within them
+const ONNX_DOMAINS: &[&str] = &["", "ai.onnx", "ai.onnx.ml"];
+
+// Dimension of a tensor shape: a fixed size, or a name like "batch_size"
+#[derive(Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Dim {
+    Fixed(i64),
+    Symbolic(String),
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TensorInfo {
+    pub name: String,
+    pub shape: Vec<Dim>,
+    pub dtype: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Opset {
+    pub domain: String,
+    pub version: i64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct InspectionReport {
+    pub format: ModelFormat,
+    // Size and hash of the model file, not of a package around it
+    pub file_bytes: usize,
+    pub sha256: String,
+    pub manifest: Option<ModelManifest>,
+    pub producer: Option<String>,
+    pub ir_version: Option<i64>,
+    pub opsets: Vec<Opset>,
+    pub inputs: Vec<TensorInfo>,
+    pub outputs: Vec<TensorInfo>,
+    pub node_count: usize,
+    // Nodes per operator type, sorted by name
+    pub op_counts: BTreeMap<String, usize>,
+    pub parameter_count: u64,
+    pub parameter_bytes: u64,
+    // Operators tract has no implementation for
+    pub unsupported_ops: Vec<String>,
+    // Known once the model loaded
+    pub precision: Option<ModelPrecision>,
+    // Why building a runnable model at the app's input shape failed
+    pub load_error: Option<String>,
+}
+
+// Inspect a model file or package. `path` only helps detecting the format.
+pub fn inspect(path: Option<&Path>, bytes: &[u8]) -> Result<InspectionReport> {
+    let source = path
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "model".to_string());
+    check_model_bytes(&source, bytes)?;
+
+    if is_package(bytes) {
+        let package = ModelPackage::read(bytes)?;
+        let mut report = inspect_model(package.format, &package.model_bytes)?;
+        report.manifest = Some(package.manifest);
+        return Ok(report);
+    }
+
+    inspect_model(ModelFormat::detect(path, bytes), bytes)
+}
+
+fn inspect_model(format: ModelFormat, model_bytes: &[u8]) -> Result<InspectionReport> {
+    let mut report = match format {
+        ModelFormat::Onnx => inspect_onnx(model_bytes)?,
+        ModelFormat::Tflite => inspect_tflite(model_bytes),
+    };
+
+    // Loading is what actually fails, so report how far it gets
+    match LoadedModel::load(model_bytes, format, 1) {
+        Ok(model) => report.precision = Some(model.precision),
+        Err(e) => report.load_error = Some(format!("{:#}", e)),
+    }
+
+    Ok(report)
+}
+
+fn empty_report(format: ModelFormat, model_bytes: &[u8]) -> InspectionReport {
+    InspectionReport {
+        format,
+        file_bytes: model_bytes.len(),
+        sha256: sha256_hex(model_bytes),
+        manifest: None,
+        producer: None,
+        ir_version: None,
+        opsets: Vec::new(),
+        inputs: Vec::new(),
+        outputs: Vec::new(),
+        node_count: 0,
+        op_counts: BTreeMap::new(),
+        parameter_count: 0,
+        parameter_bytes: 0,
+        unsupported_ops: Vec::new(),
+        precision: None,
+        load_error: None,
+    }
+}
+
+fn inspect_onnx(model_bytes: &[u8]) -> Result<InspectionReport> {
+    let onnx = tract_onnx::onnx();
+    let proto = onnx
+        .proto_model_for_read(&mut Cursor::new(model_bytes))
+        .context("Failed to parse ONNX model")?;
+    let graph = proto.graph.unwrap_or_default();
+
+    let mut report = empty_report(ModelFormat::Onnx, model_bytes);
+    let producer = format!("{} {}", proto.producer_name, proto.producer_version);
+    report.producer = Some(producer.trim().to_string()).filter(|producer| !producer.is_empty());
+    report.ir_version = Some(proto.ir_version);
+    report.opsets = proto
+        .opset_import
+        .iter()
+        .map(|opset| Opset {
+            domain: opset.domain.clone(),
+            version: opset.version,
+        })
+        .collect();
+
+    // Older exporters list initializers among the graph inputs
+    let initializers: Vec<&str> = graph
+        .initializer
+        .iter()
+        .map(|tensor| tensor.name.as_str())
+        .collect();
+    report.inputs = graph
+        .input
+        .iter()
+        .filter(|input| !initializers.contains(&input.name.as_str()))
+        .map(onnx_tensor_info)
+        .collect();
+    report.outputs = graph.output.iter().map(onnx_tensor_info).collect();
+
+    report.node_count = graph.node.len();
+    for node in &graph.node {
+        let op = onnx_op_name(node);
+        let supported = ONNX_DOMAINS.contains(&node.domain.as_str())
+            && onnx.op_register.0.contains_key(&node.op_type);
+        if !supported && !report.unsupported_ops.contains(&op) {
+            report.unsupported_ops.push(op.clone());
+        }
+        *report.op_counts.entry(op).or_default() += 1;
+    }
+    report.unsupported_ops.sort();
+
+    // Weights are initializers, or Constant nodes in some exports
+    let constants = graph
+        .node
+        .iter()
+        .filter(|node| node.op_type == "Constant" && ONNX_DOMAINS.contains(&node.domain.as_str()))
+        .flat_map(|node| &node.attribute);
+    let sizes = graph
+        .initializer
+        .iter()
+        .map(onnx_tensor_size)
+        .chain(constants.map(onnx_constant_size));
+    for (elements, bytes) in sizes {
+        report.parameter_count += elements;
+        report.parameter_bytes += bytes;
+    }
+
+    Ok(report)
+}
+
+// Elements and bytes of an ONNX tensor
+fn onnx_tensor_size(tensor: &pb::TensorProto) -> (u64, u64) {
+    let elements = tensor.dims.iter().product::<i64>().max(0) as u64;
+    (elements, elements * onnx_dtype(tensor.data_type).1)
+}
+
+// Elements and bytes of the value attribute of a Constant node
+fn onnx_constant_size(attribute: &pb::AttributeProto) -> (u64, u64) {
+    match attribute.name.as_str() {
+        "value" => attribute.t.as_ref().map(onnx_tensor_size).unwrap_or((0, 0)),
+        "value_float" => (1, 4),
+        "value_int" => (1, 8),
+        "value_floats" => (
+            attribute.floats.len() as u64,
+            attribute.floats.len() as u64 * 4,
+        ),
+        "value_ints" => (attribute.ints.len() as u64, attribute.ints.len() as u64 * 8),
+        _ => (0, 0),
+    }
+}
+
+// Operators outside the standard domains keep their domain, e.g. "com.microsoft:QLinearAdd"
+fn onnx_op_name(node: &pb::NodeProto) -> String {
+    if ONNX_DOMAINS.contains(&node.domain.as_str()) {
+        node.op_type.clone()
+    } else {
+        format!("{}:{}", node.domain, node.op_type)
+    }
+}
+
+fn onnx_tensor_info(value: &pb::ValueInfoProto) -> TensorInfo {
+    use pb::tensor_shape_proto::dimension::Value;
+    use pb::type_proto::Value as TypeValue;
+
+    let tensor = match value.r#type.as_ref().and_then(|t| t.value.as_ref()) {
+        Some(TypeValue::TensorType(tensor)) => Some(tensor),
+        _ => None,
+    };
+    let shape = tensor
+        .and_then(|tensor| tensor.shape.as_ref())
+        .map(|shape| {
+            shape
+                .dim
+                .iter()
+                .map(|dim| match &dim.value {
+                    Some(Value::DimValue(size)) => Dim::Fixed(*size),
+                    Some(Value::DimParam(name)) => Dim::Symbolic(name.clone()),
+                    None => Dim::Symbolic("?".to_string()),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let dtype = tensor
+        .map(|tensor| onnx_dtype(tensor.elem_type).0.to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    TensorInfo {
+        name: value.name.clone(),
+        shape,
+        dtype,
+    }
+}
+
+// Name and element size of an ONNX TensorProto.DataType
+fn onnx_dtype(data_type: i32) -> (&'static str, u64) {
+    match data_type {
+        1 => ("float32", 4),
+        2 => ("uint8", 1),
+        3 => ("int8", 1),
+        4 => ("uint16", 2),
+        5 => ("int16", 2),
+        6 => ("int32", 4),
+        7 => ("int64", 8),
+        8 => ("string", 0),
+        9 => ("bool", 1),
+        10 => ("float16", 2),
+        11 => ("float64", 8),
+        12 => ("uint32", 4),
+        13 => ("uint64", 8),
+        14 => ("complex64", 8),
+        15 => ("complex128", 16),
+        16 => ("bfloat16", 2),
+        _ => ("unknown", 0),
+    }
+}
+
+// The TFLite loader stops at the first operator it does not know, so an
+// unparseable file only gets its load error
+#[cfg(feature = "tflite")]
+fn inspect_tflite(model_bytes: &[u8]) -> InspectionReport {
+    let mut report = empty_report(ModelFormat::Tflite, model_bytes);
+    let model = match tract_tflite::tflite().model_for_read(&mut Cursor::new(model_bytes)) {
+        Ok(model) => model,
+        Err(e) => {
+            report.load_error = Some(format!("{:#}", e));
+            return report;
+        }
+    };
+
+    let tensor_info = |outlet: &OutletId| {
+        let fact = model.outlet_fact(*outlet).ok();
+        TensorInfo {
+            name: model.node(outlet.node).name.clone(),
+            shape: fact
+                .map(|fact| {
+                    fact.shape
+                        .iter()
+                        .map(|dim| match dim.to_i64() {
+                            Ok(size) => Dim::Fixed(size),
+                            Err(_) => Dim::Symbolic(dim.to_string()),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            dtype: fact
+                .map(|fact| format!("{:?}", fact.datum_type).to_lowercase())
+                .unwrap_or_else(|| "unknown".to_string()),
+        }
+    };
+    let inputs = model.input_outlets().unwrap_or_default();
+    report.inputs = inputs.iter().map(tensor_info).collect();
+    report.outputs = model
+        .output_outlets()
+        .unwrap_or_default()
+        .iter()
+        .map(tensor_info)
+        .collect();
+
+    // Weights are constant nodes in the typed graph
+    for node in model.nodes() {
+        if inputs.iter().any(|input| input.node == node.id) {
+            continue;
+        }
+        match node
+            .outputs
+            .first()
+            .and_then(|output| output.fact.konst.as_ref())
+        {
+            Some(tensor) => {
+                report.parameter_count += tensor.len() as u64;
+                report.parameter_bytes += (tensor.len() * tensor.datum_type().size_of()) as u64;
+            }
+            None => {
+                report.node_count += 1;
+                *report
+                    .op_counts
+                    .entry(node.op.name().to_string())
+                    .or_default() += 1;
+            }
+        }
+    }
+
+    report
+}
+
+#[cfg(not(feature = "tflite"))]
+fn inspect_tflite(model_bytes: &[u8]) -> InspectionReport {
+    let mut report = empty_report(ModelFormat::Tflite, model_bytes);
+    report.load_error = Some("Built without TFLite support".to_string());
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_models::{self, float_tensor, node};
+    use prost::Message;
+
+    fn constant(output: &str, value: pb::TensorProto) -> pb::NodeProto {
+        pb::NodeProto {
+            attribute: vec![pb::AttributeProto {
+                name: "value".to_string(),
+                t: Some(value),
+                ..Default::default()
+            }],
+            ..node("Constant", &[], &[output])
+        }
+    }
+
+    #[test]
+    fn describes_onnx_graph() {
+        // Scores are scaled by an initializer and shifted by a Constant node,
+        // and a contrib operator nobody reads sits next to them
+        let mut nodes = vec![
+            node("GlobalAveragePool", &["input"], &["pooled"]),
+            node("Flatten", &["pooled"], &["flat"]),
+            node("Mul", &["flat", "weight"], &["scaled"]),
+            constant("bias", float_tensor("", &[1, 3], &[0.1, 0.2, 0.3])),
+            node("Add", &["scaled", "bias"], &["scores"]),
+        ];
+        nodes.push(pb::NodeProto {
+            domain: "com.microsoft".to_string(),
+            ..node("QLinearAdd", &["scores"], &["unused"])
+        });
+        let initializers = vec![float_tensor("weight", &[1, 3], &[1.0, 2.0, 3.0])];
+        let bytes = test_models::model(nodes, initializers).encode_to_vec();
+
+        let report = inspect(Some(Path::new("model.onnx")), &bytes).unwrap();
+        assert_eq!(report.file_bytes, bytes.len());
+        assert_eq!(report.ir_version, Some(7));
+
+        let input = &report.inputs[0];
+        assert_eq!(report.inputs.len(), 1);
+        assert_eq!(input.name, "input");
+        assert_eq!(input.dtype, "float32");
+        assert!(matches!(
+            input.shape[..],
+            [
+                Dim::Fixed(1),
+                Dim::Fixed(3),
+                Dim::Fixed(224),
+                Dim::Fixed(224)
+            ]
+        ));
+        let output = &report.outputs[0];
+        assert_eq!(report.outputs.len(), 1);
+        assert_eq!(output.name, "scores");
+        assert!(matches!(output.shape[..], [Dim::Fixed(1), Dim::Fixed(3)]));
+
+        assert_eq!(report.node_count, 6);
+        let op_counts: Vec<(&str, usize)> = report
+            .op_counts
+            .iter()
+            .map(|(op, count)| (op.as_str(), *count))
+            .collect();
+        assert_eq!(
+            op_counts,
+            [
+                ("Add", 1),
+                ("Constant", 1),
+                ("Flatten", 1),
+                ("GlobalAveragePool", 1),
+                ("Mul", 1),
+                ("com.microsoft:QLinearAdd", 1),
+            ]
+        );
+        assert_eq!(report.unsupported_ops, ["com.microsoft:QLinearAdd"]);
+
+        // Three weights and three biases of 4 bytes each
+        assert_eq!(report.parameter_count, 6);
+        assert_eq!(report.parameter_bytes, 24);
+    }
+
+    #[test]
+    fn counts_scalar_and_list_constants() {
+        let attribute = |name: &str| pb::AttributeProto {
+            name: name.to_string(),
+            floats: vec![1.0, 2.0],
+            ints: vec![1, 2, 3],
+            ..Default::default()
+        };
+
+        assert_eq!(onnx_constant_size(&attribute("value_float")), (1, 4));
+        assert_eq!(onnx_constant_size(&attribute("value_int")), (1, 8));
+        assert_eq!(onnx_constant_size(&attribute("value_floats")), (2, 8));
+        assert_eq!(onnx_constant_size(&attribute("value_ints")), (3, 24));
+        assert_eq!(onnx_constant_size(&attribute("value_string")), (0, 0));
+    }
+}
@@ -0,0 +1,292 @@
+// Integrity checks on model bytes before tract sees them: a sniff for files
+// that cannot be models, SHA-256 checksums and Ed25519 signatures. Without
+// them a truncated download or a saved web page only shows up as an opaque
+// parse error.
+use crate::hash::sha256_hex;
+use crate::model_manager::ModelError;
+use base64::{engine::general_purpose, Engine as _};
+use ed25519_dalek::{Signature, VerifyingKey};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+// Sidecar files next to a model, e.g. "model.onnx.sha256" and "model.onnx.sig"
+pub const CHECKSUM_EXTENSION: &str = "sha256";
+pub const SIGNATURE_EXTENSION: &str = "sig";
+
+pub fn sidecar_path(model_path: &Path, extension: &str) -> PathBuf {
+    let mut path = model_path.as_os_str().to_owned();
+    path.push(".");
+    path.push(extension);
+    PathBuf::from(path)
+}
+
+// Catch the usual stand-ins for a model file. Neither ONNX protobufs nor
+// TFLite flatbuffers can start with text like this.
+pub fn check_model_bytes(source: &str, model_bytes: &[u8]) -> Result<(), ModelError> {
+    let first_byte = model_bytes
+        .iter()
+        .find(|byte| !byte.is_ascii_whitespace())
+        .copied();
+
+    let reason = if model_bytes.is_empty() {
+        "the file is empty"
+    } else if model_bytes.starts_with(b"version https://git-lfs") {
+        "it is a Git LFS pointer, fetch the actual file with `git lfs pull`"
+    } else if first_byte == Some(b'<') {
+        "it is an HTML or XML document, likely a saved web page instead of the download"
+    } else {
+        return Ok(());
+    };
+
+    Err(ModelError::NotAModel {
+        model: source.to_string(),
+        reason: reason.to_string(),
+    })
+}
+
+// Parse a checksum as written by `sha256sum`: the hex digest, optionally
+// followed by the file name
+pub fn parse_checksum(source: &str, contents: &str) -> Result<String, ModelError> {
+    let digest = contents.split_whitespace().next().unwrap_or_default();
+    if digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(ModelError::InvalidChecksum(format!(
+            "{}: expected a hex SHA-256 digest",
+            source
+        )));
+    }
+    Ok(digest.to_ascii_lowercase())
+}
+
+// Checksum from the `.sha256` sidecar of `model_path`; None if there is none
+pub fn read_checksum_file(model_path: &Path) -> Result<Option<String>, ModelError> {
+    let path = sidecar_path(model_path, CHECKSUM_EXTENSION);
+    match fs::read_to_string(&path) {
+        Ok(contents) => parse_checksum(&path.to_string_lossy(), &contents).map(Some),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(ModelError::InvalidChecksum(format!(
+            "Failed to read {:?}: {}",
+            path, e
+        ))),
+    }
+}
+
+// `expected` must already be parsed, i.e. lowercase hex
+pub fn verify_checksum(source: &str, model_bytes: &[u8], expected: &str) -> Result<(), ModelError> {
+    let actual = sha256_hex(model_bytes);
+    if actual != expected {
+        return Err(ModelError::ChecksumMismatch {
+            model: source.to_string(),
+            expected: expected.to_string(),
+            actual,
+        });
+    }
+    Ok(())
+}
+
+// Base64 signature from the `.sig` sidecar of `model_path`; None if there is none
+pub fn read_signature_file(model_path: &Path) -> Result<Option<String>, ModelError> {
+    let path = sidecar_path(model_path, SIGNATURE_EXTENSION);
+    match fs::read_to_string(&path) {
+        Ok(contents) => Ok(Some(contents.trim().to_string())),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(ModelError::InvalidSignature(format!(
+            "Failed to read {:?}: {}",
+            path, e
+        ))),
+    }
+}
+
+// Ed25519 public keys whose signatures are accepted
+#[derive(Debug, Clone, Default)]
+pub struct TrustedKeys {
+    keys: Vec<VerifyingKey>,
+}
+
+impl TrustedKeys {
+    // One base64 encoded 32 byte key per line; blank lines and lines
+    // starting with '#' are ignored
+    pub fn parse(contents: &str) -> Result<Self, ModelError> {
+        let keys = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(parse_key)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { keys })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    // `signature` is the base64 encoded signature over the whole model file
+    pub fn verify(
+        &self,
+        source: &str,
+        model_bytes: &[u8],
+        signature: &str,
+    ) -> Result<(), ModelError> {
+        let bytes: [u8; 64] = general_purpose::STANDARD
+            .decode(signature.trim())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| {
+                ModelError::InvalidSignature(format!(
+                    "{}: expected a base64 encoded 64 byte Ed25519 signature",
+                    source
+                ))
+            })?;
+        let signature = Signature::from_bytes(&bytes);
+
+        let trusted = self
+            .keys
+            .iter()
+            .any(|key| key.verify_strict(model_bytes, &signature).is_ok());
+        if !trusted {
+            return Err(ModelError::UntrustedSignature(source.to_string()));
+        }
+        Ok(())
+    }
+}
+
+fn parse_key(line: &str) -> Result<VerifyingKey, ModelError> {
+    let bytes: [u8; 32] = general_purpose::STANDARD
+        .decode(line)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| {
+            ModelError::InvalidKey(format!(
+                "{}: expected a base64 encoded 32 byte Ed25519 public key",
+                line
+            ))
+        })?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| ModelError::InvalidKey(format!("{}: {}", line, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    const DIGEST: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+    fn not_a_model(bytes: &[u8]) -> String {
+        match check_model_bytes("model.onnx", bytes) {
+            Err(ModelError::NotAModel { reason, .. }) => reason,
+            other => panic!("expected NotAModel, got {:?}", other),
+        }
+    }
+
+    fn signing_key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn trusted_keys(keys: &[&SigningKey]) -> TrustedKeys {
+        let lines: Vec<String> = keys
+            .iter()
+            .map(|key| general_purpose::STANDARD.encode(key.verifying_key().to_bytes()))
+            .collect();
+        TrustedKeys::parse(&lines.join("\n")).unwrap()
+    }
+
+    fn sign(key: &SigningKey, bytes: &[u8]) -> String {
+        general_purpose::STANDARD.encode(key.sign(bytes).to_bytes())
+    }
+
+    #[test]
+    fn rejects_common_stand_ins() {
+        assert!(not_a_model(b"").contains("empty"));
+        assert!(not_a_model(b"version https://git-lfs.github.com/spec/v1\n").contains("LFS"));
+        assert!(not_a_model(b"\n  <!DOCTYPE html><html>").contains("HTML"));
+
+        // An ONNX protobuf starts with a field tag
+        assert!(check_model_bytes("model.onnx", &[0x08, 0x07, 0x12]).is_ok());
+    }
+
+    #[test]
+    fn parses_sha256sum_output() {
+        let line = format!("{}  mobilenet_v2.onnx\n", DIGEST);
+        assert_eq!(parse_checksum("sidecar", &line).unwrap(), DIGEST);
+        assert_eq!(
+            parse_checksum("sidecar", &DIGEST.to_ascii_uppercase()).unwrap(),
+            DIGEST
+        );
+
+        for invalid in ["", "abc123", &DIGEST[1..], &DIGEST.replace('5', "g")] {
+            assert!(
+                matches!(
+                    parse_checksum("sidecar", invalid),
+                    Err(ModelError::InvalidChecksum(_))
+                ),
+                "{:?}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn verifies_checksums() {
+        assert!(verify_checksum("model", b"hello\n", DIGEST).is_ok());
+        assert!(matches!(
+            verify_checksum("model", b"hello", DIGEST),
+            Err(ModelError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn missing_sidecar_is_not_an_error() {
+        let model = std::env::temp_dir().join(format!(
+            "taurivision-integrity-{}-missing.onnx",
+            std::process::id()
+        ));
+        assert!(read_checksum_file(&model).unwrap().is_none());
+        assert!(read_signature_file(&model).unwrap().is_none());
+    }
+
+    #[test]
+    fn accepts_signature_of_trusted_key() {
+        let key = signing_key(1);
+        let keys = trusted_keys(&[&signing_key(2), &key]);
+
+        let signature = sign(&key, b"model bytes");
+        assert!(keys.verify("model", b"model bytes", &signature).is_ok());
+    }
+
+    #[test]
+    fn rejects_untrusted_or_invalid_signatures() {
+        let key = signing_key(1);
+        let keys = trusted_keys(&[&key]);
+
+        // Signed by another key, or over other bytes
+        let other = sign(&signing_key(2), b"model bytes");
+        assert!(matches!(
+            keys.verify("model", b"model bytes", &other),
+            Err(ModelError::UntrustedSignature(_))
+        ));
+        let tampered = sign(&key, b"model bytes");
+        assert!(matches!(
+            keys.verify("model", b"model bytes!", &tampered),
+            Err(ModelError::UntrustedSignature(_))
+        ));
+
+        for invalid in ["not base64!", "c2hvcnQ="] {
+            assert!(matches!(
+                keys.verify("model", b"model bytes", invalid),
+                Err(ModelError::InvalidSignature(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn parses_trusted_keys() {
+        let key = general_purpose::STANDARD.encode(signing_key(1).verifying_key().to_bytes());
+        let keys = TrustedKeys::parse(&format!("# release key\n\n{}\n", key)).unwrap();
+        assert!(!keys.is_empty());
+
+        assert!(matches!(
+            TrustedKeys::parse("c2hvcnQ="),
+            Err(ModelError::InvalidKey(_))
+        ));
+    }
+}
@@ -0,0 +1,1169 @@
+use crate::ensemble::{Ensemble, EnsembleConfig, LabelMap};
+use crate::hash::sha256_hex;
+use crate::model_cache::ModelCache;
+use crate::model_format::{LoadedModel, ModelFormat, ModelPrecision};
+use crate::model_integrity::{
+    check_model_bytes, read_checksum_file, read_signature_file, sidecar_path, TrustedKeys,
+    CHECKSUM_EXTENSION, SIGNATURE_EXTENSION,
+};
+use crate::model_package::{is_package, Activation, ModelManifest, ModelPackage};
+use anyhow::{Context, Result};
+use log::{debug, error, info, warn};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ModelError {
+    #[error("Model not initialized")]
+    NotInitialized,
+
+    #[error("Failed to load model: {0}")]
+    LoadError(String),
+
+    #[error("Inference error: {0}")]
+    InferenceError(String),
+
+    #[error("Unknown model: {0}")]
+    UnknownModel(String),
+
+    #[error("Invalid model package: {0}")]
+    InvalidPackage(String),
+
+    #[error("{model} is not a model file: {reason}")]
+    NotAModel { model: String, reason: String },
+
+    #[error("No checksum for {0}: expected a model package or a .sha256 file next to it")]
+    ChecksumMissing(String),
+
+    #[error("Invalid checksum: {0}")]
+    InvalidChecksum(String),
+
+    #[error("Checksum mismatch for {model}: expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        model: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("{0} is not signed: expected a .sig file next to it")]
+    SignatureMissing(String),
+
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+
+    #[error("Signature of {0} does not match any trusted key")]
+    UntrustedSignature(String),
+
+    #[error("Invalid trusted key: {0}")]
+    InvalidKey(String),
+
+    #[error("Invalid ensemble {0}")]
+    InvalidEnsemble(String),
+}
+
+// Side length of the square model input (mobilenet expects 224x224)
+pub const INPUT_SIZE: usize = 224;
+
+// Number of labels returned by `recognize`
+pub const DEFAULT_TOP_K: usize = 5;
+
+// Public description of a registered model
+#[derive(Serialize, Debug, Clone)]
+pub struct ModelInfo {
+    pub id: String,
+    pub source: String,
+    pub num_labels: usize,
+    pub input_shape: Vec<usize>,
+    // Hex SHA-256 of the model file
+    pub sha256: String,
+    pub format: ModelFormat,
+    pub precision: ModelPrecision,
+    // Set for models loaded from a package
+    pub manifest: Option<ModelManifest>,
+    // Set for ensembles, which combine other registered models
+    pub ensemble: Option<EnsembleConfig>,
+}
+
+// What registration demands of a model file beyond being loadable
+#[derive(Clone, Default)]
+struct Checks {
+    // Fail without a package manifest or .sha256 sidecar instead of loading unverified
+    require_checksum: bool,
+    // Require a .sig sidecar signed by one of these keys
+    signers: Option<TrustedKeys>,
+}
+
+// Where a registered model was loaded from, so it can be loaded again
+#[derive(Clone)]
+struct ModelOrigin {
+    model_path: PathBuf,
+    labels_path: PathBuf,
+    checks: Checks,
+}
+
+// What answers recognition requests for a registered model
+enum Inference {
+    Model {
+        model: LoadedModel,
+        labels: Vec<String>,
+    },
+    Ensemble(Ensemble),
+}
+
+struct RegisteredModel {
+    info: ModelInfo,
+    inference: Inference,
+    // None for models loaded from memory and ensembles
+    origin: Option<ModelOrigin>,
+}
+
+impl RegisteredModel {
+    fn labels(&self) -> &[String] {
+        match &self.inference {
+            Inference::Model { labels, .. } => labels,
+            Inference::Ensemble(ensemble) => ensemble.labels(),
+        }
+    }
+
+    fn recognize(&self, image_data: &[f32], top_k: usize) -> Result<Vec<(String, f32)>> {
+        // A short buffer would otherwise panic while building the tensor
+        let expected_len = INPUT_SIZE * INPUT_SIZE * 3;
+        if image_data.len() != expected_len {
+            return Err(ModelError::InferenceError(format!(
+                "Expected {} input values, got {}",
+                expected_len,
+                image_data.len()
+            ))
+            .into());
+        }
+
+        let start_time = Instant::now();
+
+        let top_results = match &self.inference {
+            Inference::Model { model, labels } => {
+                top_k_labels(self.scores(model, image_data)?, labels, top_k)
+            }
+            Inference::Ensemble(ensemble) => ensemble.recognize(image_data, top_k)?,
+        };
+
+        let elapsed = start_time.elapsed();
+        info!(
+            "Inference with {} completed in {:.2?}",
+            self.info.id, elapsed
+        );
+
+        Ok(top_results)
+    }
+
+    fn scores(&self, model: &LoadedModel, image_data: &[f32]) -> Result<Vec<f32>> {
+        // Packaged models declare their own normalization and output activation
+        let manifest = self.info.manifest.as_ref();
+        let normalized;
+        let image_data = match manifest.map(|m| &m.normalization) {
+            Some(normalization) if !normalization.is_identity() => {
+                normalized = normalization.apply(image_data);
+                normalized.as_slice()
+            }
+            _ => image_data,
+        };
+
+        // The model packs the HWC data into the tensor layout it expects
+        let mut scores = model
+            .run(&[image_data])?
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        if let Some(manifest) = manifest {
+            manifest.postprocessing.activation.apply(&mut scores);
+        }
+
+        Ok(scores)
+    }
+
+    // Scores as probabilities, so models with differently scaled outputs can be combined
+    fn probabilities(&self, image_data: &[f32]) -> Result<Vec<f32>> {
+        let Inference::Model { model, .. } = &self.inference else {
+            return Err(ModelError::InferenceError(format!(
+                "{} is an ensemble and cannot be combined further",
+                self.info.id
+            ))
+            .into());
+        };
+
+        let mut scores = self.scores(model, image_data)?;
+        let activation = self
+            .info
+            .manifest
+            .as_ref()
+            .map(|manifest| manifest.postprocessing.activation)
+            .unwrap_or_default();
+        // Models without a declared activation mostly output logits
+        if activation == Activation::None && !is_distribution(&scores) {
+            Activation::Softmax.apply(&mut scores);
+        }
+        Ok(scores)
+    }
+}
+
+fn is_distribution(scores: &[f32]) -> bool {
+    let sum: f32 = scores.iter().sum();
+    scores.iter().all(|score| (0.0..=1.0).contains(score)) && (sum - 1.0).abs() < 0.01
+}
+
+// A registered model as of when the handle was taken. Reloading the model
+// registers a new one for later handles while this one keeps working.
+#[derive(Clone)]
+pub struct ModelHandle(Arc<RegisteredModel>);
+
+impl ModelHandle {
+    pub fn info(&self) -> &ModelInfo {
+        &self.0.info
+    }
+
+    // Labels in output index order
+    pub fn labels(&self) -> &[String] {
+        self.0.labels()
+    }
+
+    pub fn recognize(&self, image_data: &[f32], top_k: usize) -> Result<Vec<(String, f32)>> {
+        self.0.recognize(image_data, top_k)
+    }
+
+    pub(crate) fn probabilities(&self, image_data: &[f32]) -> Result<Vec<f32>> {
+        self.0.probabilities(image_data)
+    }
+}
+
+// A loaded and verified model, not registered yet
+pub struct PreparedModel {
+    model_id: String,
+    source: String,
+    sha256: String,
+    model: LoadedModel,
+    labels: Vec<String>,
+    manifest: Option<ModelManifest>,
+    origin: Option<ModelOrigin>,
+}
+
+// Loads a model from its files, to register it or to reload a registered
+// one. It holds no reference to the manager, so the slow part can run while
+// the manager keeps serving.
+pub struct LoadJob {
+    model_id: String,
+    origin: ModelOrigin,
+    cache: Option<ModelCache>,
+}
+
+impl LoadJob {
+    pub fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    // Read, verify and build the model, with the same checks as when it was registered
+    pub fn run(self) -> Result<PreparedModel> {
+        prepare_from_paths(&self.model_id, self.origin, self.cache.as_ref())
+    }
+}
+
+// Registry of loaded models keyed by model id
+pub struct ModelManager {
+    models: HashMap<String, Arc<RegisteredModel>>,
+    default_model: Option<String>,
+    cache: Option<ModelCache>,
+}
+
+impl ModelManager {
+    pub fn new() -> Self {
+        Self {
+            models: HashMap::new(),
+            default_model: None,
+            cache: None,
+        }
+    }
+
+    // Keep prepared models in `cache` so later loads skip parsing and analysis
+    pub fn set_model_cache(&mut self, cache: ModelCache) {
+        self.cache = Some(cache);
+    }
+
+    pub fn init(&mut self) -> Result<()> {
+        // Get platform-specific paths
+        let model_path = self.get_model_path();
+        let labels_path = self.get_labels_path();
+
+        info!("Loading model from {:?}", model_path);
+        info!("Loading labels from {:?}", labels_path);
+
+        // Load the model using the determined paths
+        self.init_with_paths(model_path, labels_path)
+    }
+
+    // Get the appropriate model path based on platform
+    pub fn get_model_path(&self) -> PathBuf {
+        // Improved platform detection
+        #[cfg(target_os = "android")]
+        {
+            debug!("Running on Android, using Android-specific path");
+            // For Android, use the path directly in assets without the "model" subdirectory
+            PathBuf::from("mobilenet_v2.onnx")
+        }
+
+        #[cfg(not(target_os = "android"))]
+        {
+            debug!("Running on desktop, using desktop-specific path");
+            // For desktop, use the original path
+            PathBuf::from("assets/model/mobilenet_v2.onnx")
+        }
+    }
+
+    // Get the appropriate labels path based on platform
+    pub fn get_labels_path(&self) -> PathBuf {
+        #[cfg(target_os = "android")]
+        {
+            debug!("Using Android-specific labels path");
+            // For Android, use the path directly in assets without the "model" subdirectory
+            PathBuf::from("labels.txt")
+        }
+
+        #[cfg(not(target_os = "android"))]
+        {
+            debug!("Using desktop-specific labels path");
+            // For desktop, use the original path
+            PathBuf::from("assets/model/labels.txt")
+        }
+    }
+
+    // Initialize with explicit paths (useful for Tauri's resource resolution)
+    // The model is registered under its file stem and becomes the default model.
+    // It must be a package or have a .sha256 sidecar, see `register_verified`.
+    pub fn init_with_paths(&mut self, model_path: PathBuf, labels_path: PathBuf) -> Result<()> {
+        let model_id = model_id_from_path(&model_path);
+        self.register_verified(&model_id, &model_path, &labels_path)?;
+        self.default_model = Some(model_id);
+        Ok(())
+    }
+
+    // Load a model and its labels and register it under `model_id`.
+    // Model packages carry their own labels, so `labels_path` is ignored for them.
+    // A .sha256 sidecar next to the model is verified when present.
+    pub fn register(
+        &mut self,
+        model_id: &str,
+        model_path: &Path,
+        labels_path: &Path,
+    ) -> Result<ModelInfo> {
+        self.register_checked(model_id, model_path, labels_path, Checks::default())
+    }
+
+    // Like `register`, but the model must come with a checksum, from its package
+    // manifest or a .sha256 sidecar
+    pub fn register_verified(
+        &mut self,
+        model_id: &str,
+        model_path: &Path,
+        labels_path: &Path,
+    ) -> Result<ModelInfo> {
+        let checks = Checks {
+            require_checksum: true,
+            ..Checks::default()
+        };
+        self.register_checked(model_id, model_path, labels_path, checks)
+    }
+
+    // Like `register`, but the model must come with a .sig sidecar holding an
+    // Ed25519 signature of the model file by one of `trusted_keys`
+    pub fn register_signed(
+        &mut self,
+        model_id: &str,
+        model_path: &Path,
+        labels_path: &Path,
+        trusted_keys: &TrustedKeys,
+    ) -> Result<ModelInfo> {
+        let checks = Checks {
+            signers: Some(trusted_keys.clone()),
+            ..Checks::default()
+        };
+        self.register_checked(model_id, model_path, labels_path, checks)
+    }
+
+    fn register_checked(
+        &mut self,
+        model_id: &str,
+        model_path: &Path,
+        labels_path: &Path,
+        checks: Checks,
+    ) -> Result<ModelInfo> {
+        let origin = ModelOrigin {
+            model_path: model_path.to_path_buf(),
+            labels_path: labels_path.to_path_buf(),
+            checks,
+        };
+        let prepared = prepare_from_paths(model_id, origin, self.cache.as_ref())?;
+        Ok(self.insert(prepared))
+    }
+
+    // Initialize from in-memory model and labels data (e.g. bytes embedded in the app binary)
+    // The model is registered under `model_id` and becomes the default model
+    pub fn init_from_bytes(
+        &mut self,
+        model_id: &str,
+        model_bytes: &[u8],
+        labels_bytes: &[u8],
+    ) -> Result<()> {
+        info!("Initializing model {} from memory", model_id);
+
+        debug!("Model size: {} bytes", model_bytes.len());
+        debug!("Labels size: {} bytes", labels_bytes.len());
+
+        check_model_bytes("embedded model", model_bytes)?;
+        let sha256 = sha256_hex(model_bytes);
+        let source = "embedded".to_string();
+        let prepared = if is_package(model_bytes) {
+            prepare_package(model_id, source, model_bytes, sha256, self.cache.as_ref())?
+        } else {
+            let format = ModelFormat::detect(None, model_bytes);
+            let model = load_model(model_bytes, &sha256, format, self.cache.as_ref())
+                .context("Failed to load model from memory")?;
+
+            // Load labels from bytes
+            let labels_str = std::str::from_utf8(labels_bytes)
+                .context("Failed to convert labels bytes to string")?;
+
+            let labels = parse_labels(labels_str);
+
+            debug!("Parsed {} labels from memory", labels.len());
+
+            PreparedModel {
+                model_id: model_id.to_string(),
+                source,
+                sha256,
+                model,
+                labels,
+                manifest: None,
+                origin: None,
+            }
+        };
+
+        // Store the model
+        self.insert(prepared);
+        self.default_model = Some(model_id.to_string());
+
+        info!("Model initialization from memory successful");
+        Ok(())
+    }
+
+    // Store a loaded model, replacing any previous model with the same id.
+    // Handles to the previous model keep it alive until they are dropped.
+    fn insert(&mut self, prepared: PreparedModel) -> ModelInfo {
+        let model_id = prepared.model_id;
+        let info = ModelInfo {
+            id: model_id.clone(),
+            source: prepared.source,
+            num_labels: prepared.labels.len(),
+            input_shape: prepared.model.input_shape(),
+            sha256: prepared.sha256,
+            format: prepared.model.format,
+            precision: prepared.model.precision,
+            manifest: prepared.manifest,
+            ensemble: None,
+        };
+
+        self.models.insert(
+            model_id.clone(),
+            Arc::new(RegisteredModel {
+                info: info.clone(),
+                inference: Inference::Model {
+                    model: prepared.model,
+                    labels: prepared.labels,
+                },
+                origin: prepared.origin,
+            }),
+        );
+
+        if self.default_model.is_none() {
+            self.default_model = Some(model_id.clone());
+        }
+
+        info!(
+            "Model {} ({:?}, {:?}) initialized successfully",
+            model_id, info.format, info.precision
+        );
+        self.refresh_ensembles(&model_id);
+        info
+    }
+
+    // Register a virtual model combining registered models. Label maps are
+    // read now; the ensemble follows its members when they are reloaded.
+    pub fn register_ensemble(&mut self, config: &EnsembleConfig) -> Result<ModelInfo> {
+        if self.has_model(&config.id) {
+            return Err(ModelError::InvalidEnsemble(format!(
+                "{}: the id is already in use",
+                config.id
+            ))
+            .into());
+        }
+
+        let label_maps = config
+            .members
+            .iter()
+            .map(|member| member.label_map.as_deref().map(LabelMap::load).transpose())
+            .collect::<Result<Vec<_>>>()?;
+        let info = self.build_ensemble(config.clone(), label_maps)?;
+        info!(
+            "Ensemble {} of {} models initialized successfully",
+            info.id,
+            config.members.len()
+        );
+        Ok(info)
+    }
+
+    // Unregister an ensemble; returns whether there was one under `model_id`
+    pub fn remove_ensemble(&mut self, model_id: &str) -> bool {
+        let Some(Inference::Ensemble(ensemble)) = self
+            .models
+            .get(model_id)
+            .map(|registered| &registered.inference)
+        else {
+            return false;
+        };
+
+        // Fall back to a member, which is a model of the same label space
+        if self.default_model.as_deref() == Some(model_id) {
+            self.default_model = ensemble.members().next().map(|m| m.info().id.clone());
+        }
+        self.models.remove(model_id);
+        true
+    }
+
+    fn build_ensemble(
+        &mut self,
+        config: EnsembleConfig,
+        label_maps: Vec<Option<LabelMap>>,
+    ) -> Result<ModelInfo> {
+        let invalid =
+            |reason: String| ModelError::InvalidEnsemble(format!("{}: {}", config.id, reason));
+        if config.members.len() < 2 {
+            return Err(invalid("needs at least two members".to_string()).into());
+        }
+
+        let mut handles = Vec::with_capacity(config.members.len());
+        for member in &config.members {
+            if !(member.weight.is_finite() && member.weight > 0.0) {
+                return Err(
+                    invalid(format!("weight of {} must be positive", member.model_id)).into(),
+                );
+            }
+            if handles
+                .iter()
+                .any(|handle: &ModelHandle| handle.info().id == member.model_id)
+            {
+                return Err(invalid(format!("{} is listed twice", member.model_id)).into());
+            }
+            let handle = self.handle(&member.model_id)?;
+            if handle.info().ensemble.is_some() {
+                return Err(invalid(format!("{} is an ensemble itself", member.model_id)).into());
+            }
+            handles.push(handle);
+        }
+
+        let ensemble = Ensemble::build(config, label_maps, handles);
+        let members: Vec<&ModelInfo> = ensemble.members().map(ModelHandle::info).collect();
+        let info = ModelInfo {
+            id: ensemble.config.id.clone(),
+            source: format!(
+                "ensemble of {}",
+                members
+                    .iter()
+                    .map(|member| member.id.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            num_labels: ensemble.labels().len(),
+            input_shape: members[0].input_shape.clone(),
+            sha256: ensemble.sha256(),
+            format: members[0].format,
+            precision: if members
+                .iter()
+                .any(|member| member.precision == ModelPrecision::Int8)
+            {
+                ModelPrecision::Int8
+            } else {
+                ModelPrecision::Float
+            },
+            manifest: None,
+            ensemble: Some(ensemble.config.clone()),
+        };
+
+        self.models.insert(
+            info.id.clone(),
+            Arc::new(RegisteredModel {
+                info: info.clone(),
+                inference: Inference::Ensemble(ensemble),
+                origin: None,
+            }),
+        );
+        Ok(info)
+    }
+
+    // Bind the ensembles using `model_id` to the model now registered under it
+    fn refresh_ensembles(&mut self, model_id: &str) {
+        let dependents: Vec<(EnsembleConfig, Vec<Option<LabelMap>>)> = self
+            .models
+            .values()
+            .filter_map(|registered| match &registered.inference {
+                Inference::Ensemble(ensemble)
+                    if ensemble
+                        .members()
+                        .any(|member| member.info().id == model_id) =>
+                {
+                    Some((ensemble.config.clone(), ensemble.label_maps.clone()))
+                }
+                _ => None,
+            })
+            .collect();
+
+        for (config, label_maps) in dependents {
+            let ensemble_id = config.id.clone();
+            // The stale ensemble keeps serving if the new member does not fit
+            if let Err(e) = self.build_ensemble(config, label_maps) {
+                warn!("Failed to update ensemble {}: {:#}", ensemble_id, e);
+            }
+        }
+    }
+
+    // Files a model was loaded from: the model, its labels and their
+    // sidecars. None for unknown models and models loaded from memory.
+    pub fn source_paths(&self, model_id: &str) -> Option<Vec<PathBuf>> {
+        let origin = self.models.get(model_id)?.origin.as_ref()?;
+        Some(vec![
+            origin.model_path.clone(),
+            origin.labels_path.clone(),
+            sidecar_path(&origin.model_path, CHECKSUM_EXTENSION),
+            sidecar_path(&origin.model_path, SIGNATURE_EXTENSION),
+        ])
+    }
+
+    // Models that run when `model_id` is used: the members of an ensemble,
+    // or the model itself. None for unknown models.
+    pub fn member_ids(&self, model_id: &str) -> Option<Vec<String>> {
+        match &self.models.get(model_id)?.inference {
+            Inference::Ensemble(ensemble) => Some(
+                ensemble
+                    .members()
+                    .map(|member| member.info().id.clone())
+                    .collect(),
+            ),
+            _ => Some(vec![model_id.to_string()]),
+        }
+    }
+
+    // Start loading a model to register under `model_id`, checked like
+    // `register`, or like `register_signed` when `trusted_keys` are given. Run
+    // the job without holding on to the manager, then pass the result to `finish_register`.
+    pub fn register_job(
+        &self,
+        model_id: &str,
+        model_path: &Path,
+        labels_path: &Path,
+        trusted_keys: Option<&TrustedKeys>,
+    ) -> LoadJob {
+        LoadJob {
+            model_id: model_id.to_string(),
+            origin: ModelOrigin {
+                model_path: model_path.to_path_buf(),
+                labels_path: labels_path.to_path_buf(),
+                checks: Checks {
+                    signers: trusted_keys.cloned(),
+                    ..Checks::default()
+                },
+            },
+            cache: self.cache.clone(),
+        }
+    }
+
+    // Register a model loaded by a `register_job`
+    pub fn finish_register(&mut self, prepared: PreparedModel) -> Result<ModelInfo> {
+        // The id may have been taken while the model was loading
+        if self.has_model(&prepared.model_id) {
+            return Err(ModelError::LoadError(format!(
+                "Model id {} is already in use",
+                prepared.model_id
+            ))
+            .into());
+        }
+        Ok(self.insert(prepared))
+    }
+
+    // Start reloading a model from the files it was registered from. Run the
+    // job without holding on to the manager, then pass the result to `finish_reload`.
+    pub fn reload_job(&self, model_id: &str) -> Result<LoadJob> {
+        let registered = self
+            .models
+            .get(model_id)
+            .ok_or_else(|| ModelError::UnknownModel(model_id.to_string()))?;
+        let origin = registered.origin.clone().ok_or_else(|| {
+            ModelError::LoadError(format!("Model {} was not loaded from a file", model_id))
+        })?;
+
+        Ok(LoadJob {
+            model_id: model_id.to_string(),
+            origin,
+            cache: self.cache.clone(),
+        })
+    }
+
+    // Swap a reloaded model in for the registered one. Inference already
+    // running, or holding a handle, finishes with the previous model.
+    pub fn finish_reload(&mut self, prepared: PreparedModel) -> Result<ModelInfo> {
+        // The model may have been replaced from elsewhere while it was reloading
+        if !self.has_model(&prepared.model_id) {
+            return Err(ModelError::UnknownModel(prepared.model_id).into());
+        }
+        Ok(self.insert(prepared))
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.default_model.is_some()
+    }
+
+    // All registered models, sorted by id
+    pub fn models(&self) -> Vec<ModelInfo> {
+        let mut models: Vec<ModelInfo> = self.models.values().map(|m| m.info.clone()).collect();
+        models.sort_by(|a, b| a.id.cmp(&b.id));
+        models
+    }
+
+    pub fn has_model(&self, model_id: &str) -> bool {
+        self.models.contains_key(model_id)
+    }
+
+    pub fn default_model_id(&self) -> Option<&str> {
+        self.default_model.as_deref()
+    }
+
+    pub fn model_info(&self, model_id: &str) -> Option<&ModelInfo> {
+        self.models.get(model_id).map(|m| &m.info)
+    }
+
+    // Labels of a registered model, in output index order
+    pub fn labels(&self, model_id: &str) -> Option<&[String]> {
+        self.models.get(model_id).map(|m| m.labels())
+    }
+
+    // The model currently registered under `model_id`, usable after the manager is unlocked
+    pub fn handle(&self, model_id: &str) -> Result<ModelHandle> {
+        self.models
+            .get(model_id)
+            .map(|registered| ModelHandle(Arc::clone(registered)))
+            .ok_or_else(|| ModelError::UnknownModel(model_id.to_string()).into())
+    }
+
+    pub fn set_default_model(&mut self, model_id: &str) -> Result<()> {
+        if !self.has_model(model_id) {
+            return Err(ModelError::UnknownModel(model_id.to_string()).into());
+        }
+
+        self.default_model = Some(model_id.to_string());
+        Ok(())
+    }
+
+    pub fn recognize(&self, image_data: &[f32]) -> Result<Vec<(String, f32)>> {
+        self.recognize_top_k(image_data, DEFAULT_TOP_K)
+    }
+
+    // Recognize with the default model and return the `top_k` highest scoring labels
+    pub fn recognize_top_k(&self, image_data: &[f32], top_k: usize) -> Result<Vec<(String, f32)>> {
+        let model_id = self
+            .default_model
+            .as_deref()
+            .ok_or(ModelError::NotInitialized)?;
+        self.recognize_with(model_id, image_data, top_k)
+    }
+
+    // Recognize with a specific registered model
+    pub fn recognize_with(
+        &self,
+        model_id: &str,
+        image_data: &[f32],
+        top_k: usize,
+    ) -> Result<Vec<(String, f32)>> {
+        self.handle(model_id)?.recognize(image_data, top_k)
+    }
+}
+
+// Read, verify and load a model and its labels from disk
+fn prepare_from_paths(
+    model_id: &str,
+    origin: ModelOrigin,
+    cache: Option<&ModelCache>,
+) -> Result<PreparedModel> {
+    let model_path = origin.model_path.as_path();
+    let labels_path = origin.labels_path.as_path();
+
+    // Log the full paths we're trying to use
+    info!("Attempting to load model from: {:?}", model_path);
+    info!("Attempting to load labels from: {:?}", labels_path);
+
+    // Try to get the current working directory for debugging
+    if let Ok(cwd) = std::env::current_dir() {
+        debug!("Current working directory: {:?}", cwd);
+    }
+
+    // Load and prepare the model
+    let model_file = match File::open(model_path) {
+        Ok(file) => {
+            debug!("Successfully opened model file");
+            file
+        }
+        Err(e) => {
+            let error_msg = format!("Failed to open model file at {:?}: {}", model_path, e);
+            error!("{}", error_msg);
+            return Err(anyhow::anyhow!(error_msg));
+        }
+    };
+
+    let mut model_file = model_file;
+
+    // Try alternative paths for Android if the first attempt fails
+    #[cfg(target_os = "android")]
+    if model_file.metadata().map(|m| m.len() == 0).unwrap_or(true) {
+        warn!("Empty model file or metadata access failed, trying alternative Android paths");
+
+        // Try with a different approach for Android asset loading
+        // This would depend on how Tauri Android handles asset loading
+        // You might need to use Tauri's asset APIs instead of direct file operations
+    }
+
+    // Read the file once, it is both hashed and parsed
+    let mut model_bytes = Vec::new();
+    model_file
+        .read_to_end(&mut model_bytes)
+        .with_context(|| format!("Failed to read model file at {:?}", model_path))?;
+    let sha256 = sha256_hex(&model_bytes);
+    let source = model_path.to_string_lossy().into_owned();
+    verify_integrity(&source, model_path, &model_bytes, &sha256, &origin.checks).map_err(|e| {
+        error!("{}", e);
+        e
+    })?;
+
+    if is_package(&model_bytes) {
+        let prepared = prepare_package(model_id, source, &model_bytes, sha256, cache)?;
+        return Ok(PreparedModel {
+            origin: Some(origin),
+            ..prepared
+        });
+    }
+
+    let format = ModelFormat::detect(Some(model_path), &model_bytes);
+    let model = load_model(&model_bytes, &sha256, format, cache).map_err(|e| {
+        error!("{:#}", e);
+        e
+    })?;
+
+    // Load class labels with more robust error handling
+    let labels = match load_labels_from_path(labels_path) {
+        Ok(labels) => {
+            info!("Labels loaded successfully");
+            labels
+        }
+        Err(e) => {
+            warn!("Failed to load labels: {}", e);
+            Vec::new()
+        }
+    };
+
+    Ok(PreparedModel {
+        model_id: model_id.to_string(),
+        source,
+        sha256,
+        model,
+        labels,
+        manifest: None,
+        origin: Some(origin),
+    })
+}
+
+// Validate a model package and load the model it contains. `sha256` is the
+// hash of the whole package, so a changed manifest counts as a new model.
+fn prepare_package(
+    model_id: &str,
+    source: String,
+    package_bytes: &[u8],
+    sha256: String,
+    cache: Option<&ModelCache>,
+) -> Result<PreparedModel> {
+    let package = ModelPackage::read(package_bytes)
+        .with_context(|| format!("Failed to read model package {}", source))?;
+    let model = load_model(&package.model_bytes, &sha256, package.format, cache).map_err(|e| {
+        error!("{:#}", e);
+        e
+    })?;
+
+    let manifest = package.manifest;
+    info!(
+        "Model {} is package {} {} ({})",
+        model_id,
+        manifest.name,
+        manifest.version,
+        manifest.license.as_deref().unwrap_or("no license given")
+    );
+
+    Ok(PreparedModel {
+        model_id: model_id.to_string(),
+        source,
+        sha256,
+        model,
+        labels: package.labels,
+        manifest: Some(manifest),
+        origin: None,
+    })
+}
+
+fn load_model(
+    model_bytes: &[u8],
+    sha256: &str,
+    format: ModelFormat,
+    cache: Option<&ModelCache>,
+) -> Result<LoadedModel> {
+    match cache {
+        Some(cache) => LoadedModel::load_cached(model_bytes, sha256, format, 1, cache),
+        None => LoadedModel::load(model_bytes, format, 1),
+    }
+}
+
+// Checks on the raw file, before any parser sees it
+fn verify_integrity(
+    source: &str,
+    model_path: &Path,
+    model_bytes: &[u8],
+    sha256: &str,
+    checks: &Checks,
+) -> Result<(), ModelError> {
+    check_model_bytes(source, model_bytes)?;
+
+    match read_checksum_file(model_path)? {
+        Some(expected) => {
+            // The bytes are hashed anyway for caching, no need to do it twice
+            if sha256 != expected {
+                return Err(ModelError::ChecksumMismatch {
+                    model: source.to_string(),
+                    expected,
+                    actual: sha256.to_string(),
+                });
+            }
+            debug!("Checksum of {} verified", source);
+        }
+        // Packages are checked against their manifest when read
+        None if checks.require_checksum && !is_package(model_bytes) => {
+            return Err(ModelError::ChecksumMissing(source.to_string()));
+        }
+        None => {}
+    }
+
+    if let Some(trusted_keys) = &checks.signers {
+        let signature = read_signature_file(model_path)?
+            .ok_or_else(|| ModelError::SignatureMissing(source.to_string()))?;
+        trusted_keys.verify(source, model_bytes, &signature)?;
+        info!("Signature of {} verified", source);
+    }
+
+    Ok(())
+}
+
+// Derive a registry id from a model file name, e.g. "mobilenet_v2.onnx" -> "mobilenet_v2"
+pub fn model_id_from_path(model_path: &Path) -> String {
+    model_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "model".to_string())
+}
+
+// Load labels from a specific path
+pub fn load_labels_from_path(labels_path: &Path) -> Result<Vec<String>> {
+    let mut file = File::open(labels_path)
+        .with_context(|| format!("Failed to open labels file at {:?}", labels_path))?;
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)
+        .context("Failed to read labels file")?;
+
+    Ok(parse_labels(&contents))
+}
+
+// Postprocess raw class scores into the `top_k` (label, score) pairs, best first
+pub fn top_k_labels(
+    scores: impl IntoIterator<Item = f32>,
+    labels: &[String],
+    top_k: usize,
+) -> Vec<(String, f32)> {
+    // The output is a 1D array of probabilities for each class
+    // Extract the values and map them to class labels
+    let mut class_scores: Vec<(String, f32)> = scores
+        .into_iter()
+        .enumerate()
+        .map(|(idx, score)| {
+            let label = labels
+                .get(idx)
+                .cloned()
+                .unwrap_or_else(|| format!("Unknown-{}", idx));
+
+            (label, score)
+        })
+        .collect();
+
+    // Sort by confidence score (descending)
+    class_scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+    class_scores.truncate(top_k);
+    class_scores
+}
+
+pub fn parse_labels(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(|line| line.trim().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ensemble::{CombineMethod, EnsembleMember};
+    use crate::test_models;
+    use std::fs;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "taurivision-model-manager-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // Writes `model_bytes` with `expected` as its .sha256 sidecar and registers
+    // it the way the bundled model is at startup
+    fn register_bundled(dir: &Path, model_bytes: &[u8], expected: &[u8]) -> ModelError {
+        let model_path = dir.join("mobilenet_v2.onnx");
+        let labels_path = dir.join("labels.txt");
+        fs::write(&model_path, model_bytes).unwrap();
+        fs::write(
+            sidecar_path(&model_path, CHECKSUM_EXTENSION),
+            sha256_hex(expected),
+        )
+        .unwrap();
+        fs::write(&labels_path, test_models::LABELS.join("\n")).unwrap();
+
+        let error = ModelManager::new()
+            .register_verified("mobilenet_v2", &model_path, &labels_path)
+            .unwrap_err();
+        error
+            .downcast::<ModelError>()
+            .unwrap_or_else(|e| panic!("expected a ModelError, got {:#}", e))
+    }
+
+    #[test]
+    fn registers_bundled_model_matching_its_checksum() {
+        let dir = temp_dir("bundled");
+        let model = test_models::float_model();
+
+        let model_path = dir.join("model.onnx");
+        let labels_path = dir.join("labels.txt");
+        fs::write(&model_path, &model).unwrap();
+        fs::write(
+            sidecar_path(&model_path, CHECKSUM_EXTENSION),
+            sha256_hex(&model),
+        )
+        .unwrap();
+        fs::write(&labels_path, test_models::LABELS.join("\n")).unwrap();
+
+        let info = ModelManager::new()
+            .register_verified("model", &model_path, &labels_path)
+            .unwrap();
+        assert_eq!(info.sha256, sha256_hex(&model));
+        assert_eq!(info.num_labels, test_models::LABELS.len());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_truncated_bundled_model() {
+        let dir = temp_dir("truncated");
+        let model = test_models::float_model();
+
+        let error = register_bundled(&dir, &model[..model.len() / 2], &model);
+        match error {
+            ModelError::ChecksumMismatch {
+                expected, actual, ..
+            } => {
+                assert_eq!(expected, sha256_hex(&model));
+                assert_ne!(actual, expected);
+            }
+            other => panic!("expected ChecksumMismatch, got {:?}", other),
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_placeholder_bundled_model() {
+        let dir = temp_dir("placeholder");
+        // What a download of the model's GitHub page instead of the raw file
+        // leaves behind. Its own digest in the sidecar does not make it a model.
+        let placeholder = b"\n\n<!DOCTYPE html>\n<html lang=\"en\">\n<head>";
+
+        let error = register_bundled(&dir, placeholder, placeholder);
+        assert!(
+            matches!(error, ModelError::NotAModel { .. }),
+            "expected NotAModel, got {:?}",
+            error
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn ensembles_are_made_of_their_members() {
+        let dir = temp_dir("members");
+        let labels_path = dir.join("labels.txt");
+        fs::write(&labels_path, test_models::LABELS.join("\n")).unwrap();
+
+        let mut model_manager = ModelManager::new();
+        for model_id in ["a", "b"] {
+            let model_path = dir.join(format!("{}.onnx", model_id));
+            fs::write(&model_path, test_models::float_model()).unwrap();
+            model_manager
+                .register(model_id, &model_path, &labels_path)
+                .unwrap();
+        }
+        let member = |model_id: &str| EnsembleMember {
+            model_id: model_id.to_string(),
+            weight: 1.0,
+            label_map: None,
+        };
+        let config = EnsembleConfig {
+            id: "ab".to_string(),
+            method: CombineMethod::default(),
+            members: vec![member("a"), member("b")],
+        };
+        model_manager.register_ensemble(&config).unwrap();
+
+        assert_eq!(model_manager.member_ids("ab").unwrap(), ["a", "b"]);
+        assert_eq!(model_manager.member_ids("a").unwrap(), ["a"]);
+        assert!(model_manager.member_ids("c").is_none());
+
+        // Only the members were loaded from files
+        assert!(model_manager.source_paths("ab").is_none());
+        let paths = model_manager.source_paths("b").unwrap();
+        assert_eq!(paths[..2], [dir.join("b.onnx"), labels_path]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,430 @@
+// Model packages: a zip archive holding the model, its labels and a manifest
+// describing how to feed the model and read its output, loaded as one unit.
+//
+//   manifest.json  name, version, license, task, input spec, normalization,
+//                  postprocessing and the SHA-256 of the model file
+//   model.onnx     or model.tflite, as named by `model_file`
+//   labels.json    JSON array of labels in output index order
+use crate::model_format::{InputLayout, ModelFormat};
+use crate::model_integrity::{check_model_bytes, parse_checksum, verify_checksum};
+use crate::model_manager::{ModelError, INPUT_SIZE};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const LABELS_FILE: &str = "labels.json";
+
+// Suggested extension for package files
+pub const PACKAGE_EXTENSION: &str = "tvmodel";
+
+const MANIFEST_VERSION: u32 = 1;
+
+// Local file header signature that every zip archive starts with
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+// Upper bounds on decompressed entry sizes, so a crafted archive cannot exhaust memory
+const MAX_MODEL_BYTES: u64 = 1024 * 1024 * 1024;
+const MAX_METADATA_BYTES: u64 = 16 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelTask {
+    Classification,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InputSpec {
+    pub width: u32,
+    pub height: u32,
+    // Defaults to the layout of the model format
+    #[serde(default)]
+    pub layout: Option<InputLayout>,
+}
+
+// Applied per channel on top of the preprocessed RGB values in [0, 1]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Normalization {
+    pub mean: [f32; 3],
+    pub std: [f32; 3],
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Self {
+            mean: [0.0; 3],
+            std: [1.0; 3],
+        }
+    }
+}
+
+impl Normalization {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    // `data` is HWC, so channels interleave
+    pub fn apply(&self, data: &[f32]) -> Vec<f32> {
+        data.iter()
+            .enumerate()
+            .map(|(index, value)| {
+                let channel = index % 3;
+                (value - self.mean[channel]) / self.std[channel]
+            })
+            .collect()
+    }
+}
+
+// Turns raw model outputs into confidences
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Activation {
+    // The model already outputs probabilities, or raw scores are wanted
+    #[default]
+    None,
+    Softmax,
+    Sigmoid,
+}
+
+impl Activation {
+    pub fn apply(self, scores: &mut [f32]) {
+        match self {
+            Activation::None => {}
+            Activation::Softmax => {
+                let max = scores.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+                let mut sum = 0.0;
+                for score in scores.iter_mut() {
+                    *score = (*score - max).exp();
+                    sum += *score;
+                }
+                if sum > 0.0 {
+                    for score in scores.iter_mut() {
+                        *score /= sum;
+                    }
+                }
+            }
+            Activation::Sigmoid => {
+                for score in scores.iter_mut() {
+                    *score = 1.0 / (1.0 + (-*score).exp());
+                }
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct Postprocessing {
+    pub activation: Activation,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Checksum {
+    // Hex SHA-256 of the model file inside the package
+    pub sha256: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ModelManifest {
+    pub manifest_version: u32,
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub license: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub task: ModelTask,
+    #[serde(default = "default_model_file")]
+    pub model_file: String,
+    pub input: InputSpec,
+    #[serde(default)]
+    pub normalization: Normalization,
+    #[serde(default)]
+    pub postprocessing: Postprocessing,
+    pub checksum: Checksum,
+}
+
+fn default_model_file() -> String {
+    "model.onnx".to_string()
+}
+
+// A package read into memory and validated
+pub struct ModelPackage {
+    pub manifest: ModelManifest,
+    pub format: ModelFormat,
+    pub model_bytes: Vec<u8>,
+    pub labels: Vec<String>,
+}
+
+pub fn is_package(bytes: &[u8]) -> bool {
+    bytes.starts_with(ZIP_MAGIC)
+}
+
+impl ModelPackage {
+    pub fn read(package_bytes: &[u8]) -> Result<Self> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(package_bytes))
+            .map_err(|e| invalid(format!("Not a zip archive: {}", e)))?;
+
+        let manifest_bytes = read_entry(&mut archive, MANIFEST_FILE, MAX_METADATA_BYTES)?;
+        let manifest: ModelManifest = serde_json::from_slice(&manifest_bytes)
+            .map_err(|e| invalid(format!("Invalid {}: {}", MANIFEST_FILE, e)))?;
+        if manifest.manifest_version != MANIFEST_VERSION {
+            return Err(invalid(format!(
+                "Unsupported manifest version {}, expected {}",
+                manifest.manifest_version, MANIFEST_VERSION
+            )));
+        }
+
+        let labels_bytes = read_entry(&mut archive, LABELS_FILE, MAX_METADATA_BYTES)?;
+        let labels: Vec<String> = serde_json::from_slice(&labels_bytes)
+            .map_err(|e| invalid(format!("Invalid {}: {}", LABELS_FILE, e)))?;
+        if labels.is_empty() {
+            return Err(invalid(format!("{} has no labels", LABELS_FILE)));
+        }
+
+        let model_bytes = read_entry(&mut archive, &manifest.model_file, MAX_MODEL_BYTES)?;
+        let format = ModelFormat::detect(Some(Path::new(&manifest.model_file)), &model_bytes);
+
+        let package = Self {
+            manifest,
+            format,
+            model_bytes,
+            labels,
+        };
+        package.validate()?;
+        Ok(package)
+    }
+
+    // Everything the manifest promises that can be checked before loading the model
+    fn validate(&self) -> Result<()> {
+        let manifest = &self.manifest;
+
+        let source = format!("{} in the package", manifest.model_file);
+        check_model_bytes(&source, &self.model_bytes)?;
+        let expected = parse_checksum(&source, &manifest.checksum.sha256)?;
+        verify_checksum(&source, &self.model_bytes, &expected)?;
+
+        // Preprocessing produces square inputs of a fixed size
+        let size = INPUT_SIZE as u32;
+        if manifest.input.width != size || manifest.input.height != size {
+            return Err(invalid(format!(
+                "Unsupported input size {}x{}, only {}x{} is supported",
+                manifest.input.width, manifest.input.height, size, size
+            )));
+        }
+        if let Some(layout) = manifest.input.layout {
+            if layout != self.format.input_layout() {
+                return Err(invalid(format!(
+                    "Input layout {:?} does not match the {:?} model, which expects {:?}",
+                    layout,
+                    self.format,
+                    self.format.input_layout()
+                )));
+            }
+        }
+        if manifest.normalization.std.contains(&0.0) {
+            return Err(invalid("Normalization std must not be zero".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+fn read_entry(
+    archive: &mut zip::ZipArchive<Cursor<&[u8]>>,
+    name: &str,
+    max_bytes: u64,
+) -> Result<Vec<u8>> {
+    let entry = archive
+        .by_name(name)
+        .map_err(|_| invalid(format!("Missing {}", name)))?;
+    if entry.size() > max_bytes {
+        return Err(invalid(format!("{} is too large", name)));
+    }
+
+    // The declared size can lie, so cap what is actually decompressed too
+    let mut contents = Vec::with_capacity(entry.size() as usize);
+    entry
+        .take(max_bytes + 1)
+        .read_to_end(&mut contents)
+        .map_err(|e| invalid(format!("Failed to read {}: {}", name, e)))?;
+    if contents.len() as u64 > max_bytes {
+        return Err(invalid(format!("{} is too large", name)));
+    }
+
+    Ok(contents)
+}
+
+fn invalid(message: String) -> anyhow::Error {
+    ModelError::InvalidPackage(message).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::sha256_hex;
+    use crate::test_models::{float_model, LABELS};
+    use serde_json::{json, Value};
+    use std::io::Write;
+
+    fn manifest(model_bytes: &[u8]) -> Value {
+        json!({
+            "manifest_version": 1,
+            "name": "Colors",
+            "version": "1.0.0",
+            "task": "classification",
+            "input": { "width": 224, "height": 224 },
+            "postprocessing": { "activation": "softmax" },
+            "checksum": { "sha256": sha256_hex(model_bytes) },
+        })
+    }
+
+    fn zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in entries {
+            writer
+                .start_file(*name, zip::write::FileOptions::default())
+                .unwrap();
+            writer.write_all(contents).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    // A package of the test model with `edit` applied to its manifest
+    fn package(edit: impl FnOnce(&mut Value)) -> Vec<u8> {
+        let model_bytes = float_model();
+        let mut manifest = manifest(&model_bytes);
+        edit(&mut manifest);
+        zip(&[
+            (MANIFEST_FILE, manifest.to_string().as_bytes()),
+            (LABELS_FILE, json!(LABELS).to_string().as_bytes()),
+            ("model.onnx", &model_bytes),
+        ])
+    }
+
+    fn read_error(package_bytes: &[u8]) -> ModelError {
+        match ModelPackage::read(package_bytes) {
+            Ok(_) => panic!("package was accepted"),
+            Err(e) => e.downcast::<ModelError>().unwrap(),
+        }
+    }
+
+    fn assert_invalid(package_bytes: &[u8], message: &str) {
+        match read_error(package_bytes) {
+            ModelError::InvalidPackage(error) => assert!(error.contains(message), "{}", error),
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn reads_a_valid_package() {
+        let package_bytes = package(|_| {});
+        assert!(is_package(&package_bytes));
+
+        let package = ModelPackage::read(&package_bytes).unwrap();
+        assert_eq!(package.format, ModelFormat::Onnx);
+        assert_eq!(package.model_bytes, float_model());
+        assert_eq!(package.labels, LABELS);
+        assert_eq!(package.manifest.name, "Colors");
+        assert_eq!(package.manifest.model_file, "model.onnx");
+        assert!(package.manifest.normalization.is_identity());
+        assert_eq!(
+            package.manifest.postprocessing.activation,
+            Activation::Softmax
+        );
+    }
+
+    #[test]
+    fn rejects_bad_zip_layouts() {
+        assert!(!is_package(&float_model()));
+        assert_invalid(&float_model(), "Not a zip archive");
+
+        let model_bytes = float_model();
+        let manifest = manifest(&model_bytes).to_string();
+        let labels = json!(LABELS).to_string();
+        assert_invalid(
+            &zip(&[
+                (LABELS_FILE, labels.as_bytes()),
+                ("model.onnx", &model_bytes),
+            ]),
+            "Missing manifest.json",
+        );
+        assert_invalid(
+            &zip(&[
+                (MANIFEST_FILE, manifest.as_bytes()),
+                ("model.onnx", &model_bytes),
+            ]),
+            "Missing labels.json",
+        );
+        assert_invalid(
+            &zip(&[
+                (MANIFEST_FILE, manifest.as_bytes()),
+                (LABELS_FILE, labels.as_bytes()),
+            ]),
+            "Missing model.onnx",
+        );
+        assert_invalid(
+            &zip(&[
+                (MANIFEST_FILE, manifest.as_bytes()),
+                (LABELS_FILE, b"[]"),
+                ("model.onnx", &model_bytes),
+            ]),
+            "has no labels",
+        );
+    }
+
+    #[test]
+    fn rejects_bad_manifests() {
+        assert_invalid(
+            &package(|manifest| {
+                manifest.as_object_mut().unwrap().remove("task");
+            }),
+            "Invalid manifest.json",
+        );
+        assert_invalid(
+            &package(|manifest| manifest["task"] = json!("detection")),
+            "Invalid manifest.json",
+        );
+        assert_invalid(
+            &package(|manifest| manifest["manifest_version"] = json!(2)),
+            "Unsupported manifest version 2",
+        );
+        assert_invalid(
+            &package(|manifest| manifest["normalization"] = json!({ "std": [0.5, 0.0, 0.5] })),
+            "std must not be zero",
+        );
+    }
+
+    #[test]
+    fn rejects_checksum_mismatches() {
+        let error = read_error(&package(|manifest| {
+            manifest["checksum"]["sha256"] = json!(sha256_hex(b"another model"));
+        }));
+        assert!(
+            matches!(error, ModelError::ChecksumMismatch { .. }),
+            "{}",
+            error
+        );
+
+        let error = read_error(&package(|manifest| {
+            manifest["checksum"]["sha256"] = json!("not a digest");
+        }));
+        assert!(matches!(error, ModelError::InvalidChecksum(_)), "{}", error);
+    }
+
+    #[test]
+    fn rejects_unsupported_inputs() {
+        assert_invalid(
+            &package(|manifest| manifest["input"] = json!({ "width": 299, "height": 299 })),
+            "Unsupported input size 299x299",
+        );
+        assert_invalid(
+            &package(|manifest| {
+                manifest["input"] = json!({ "width": 224, "height": 224, "layout": "nhwc" });
+            }),
+            "does not match",
+        );
+    }
+}
@@ -0,0 +1,521 @@
+// Sorting images into folders by predicted label or category. A plan is
+// computed first so it can be previewed; applying it writes a journal of
+// every file operation, which `undo` replays in reverse.
+use anyhow::{Context, Result};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// Bucket for images whose top confidence is below the threshold
+pub const UNCERTAIN_BUCKET: &str = "uncertain";
+
+// Bucket for labels missing from the category map
+pub const OTHER_BUCKET: &str = "other";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OrganizeMode {
+    #[default]
+    Move,
+    Copy,
+}
+
+// Rolls labels up into broader categories, e.g. "tabby" -> "cats"
+#[derive(Debug, Clone, Default)]
+pub struct CategoryMap {
+    categories: HashMap<String, String>,
+}
+
+impl CategoryMap {
+    // JSON object of category name to its labels: {"cats": ["tabby", "tiger cat"]}
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read category map {:?}", path))?;
+        Self::parse(&contents).with_context(|| format!("Invalid category map {:?}", path))
+    }
+
+    pub fn parse(contents: &str) -> Result<Self> {
+        let grouped: HashMap<String, Vec<String>> = serde_json::from_str(contents)?;
+
+        let mut categories = HashMap::new();
+        for (category, labels) in grouped {
+            for label in labels {
+                categories.insert(label.to_lowercase(), category.clone());
+            }
+        }
+
+        Ok(Self { categories })
+    }
+
+    pub fn category(&self, label: &str) -> Option<&str> {
+        self.categories
+            .get(&label.to_lowercase())
+            .map(String::as_str)
+    }
+}
+
+// An image with its top prediction, None if the model returned nothing
+#[derive(Debug, Clone)]
+pub struct OrganizeItem {
+    pub path: PathBuf,
+    pub top: Option<(String, f32)>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlannedFile {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub bucket: String,
+    pub label: Option<String>,
+    pub confidence: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OrganizePlan {
+    pub mode: OrganizeMode,
+    pub destination_root: PathBuf,
+    pub files: Vec<PlannedFile>,
+    // Images already in their bucket
+    pub unchanged: Vec<PathBuf>,
+}
+
+impl OrganizePlan {
+    // Plans are stored between preview and apply, so exactly the previewed
+    // operations are carried out
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_vec(self)?)
+            .with_context(|| format!("Failed to write plan {:?}", path))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read(path).with_context(|| format!("Failed to read plan {:?}", path))?;
+        serde_json::from_slice(&contents).with_context(|| format!("Invalid plan {:?}", path))
+    }
+}
+
+// Decide the bucket and destination of every image without touching the disk
+// beyond checking for existing files, so the plan can be previewed.
+pub fn plan_organize(
+    items: &[OrganizeItem],
+    destination_root: &Path,
+    mode: OrganizeMode,
+    categories: Option<&CategoryMap>,
+    min_confidence: f32,
+) -> OrganizePlan {
+    let mut files = Vec::new();
+    let mut unchanged = Vec::new();
+    let mut taken = HashSet::new();
+
+    for item in items {
+        let bucket = match &item.top {
+            Some((_, confidence)) if *confidence < min_confidence => UNCERTAIN_BUCKET.to_string(),
+            None => UNCERTAIN_BUCKET.to_string(),
+            Some((label, _)) => match categories {
+                Some(map) => map.category(label).unwrap_or(OTHER_BUCKET).to_string(),
+                None => label.clone(),
+            },
+        };
+        let bucket_dir = destination_root.join(folder_name(&bucket));
+
+        if item.path.parent() == Some(bucket_dir.as_path()) {
+            unchanged.push(item.path.clone());
+            continue;
+        }
+
+        let Some(file_name) = item.path.file_name() else {
+            continue;
+        };
+        let destination = unique_destination(&bucket_dir, Path::new(file_name), &taken);
+        taken.insert(destination.clone());
+
+        files.push(PlannedFile {
+            source: item.path.clone(),
+            destination,
+            bucket,
+            label: item.top.as_ref().map(|(label, _)| label.clone()),
+            confidence: item.top.as_ref().map(|(_, c)| *c).unwrap_or(0.0),
+        });
+    }
+
+    OrganizePlan {
+        mode,
+        destination_root: destination_root.to_path_buf(),
+        files,
+        unchanged,
+    }
+}
+
+// Labels can contain characters that are not valid in folder names
+fn folder_name(bucket: &str) -> String {
+    let name: String = bucket
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let name = name.trim().trim_matches('.').trim();
+
+    if name.is_empty() {
+        OTHER_BUCKET.to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+// `name.jpg`, then `name (1).jpg`, ... avoiding existing and already planned files
+fn unique_destination(dir: &Path, file_name: &Path, taken: &HashSet<PathBuf>) -> PathBuf {
+    let candidate = dir.join(file_name);
+    if !candidate.exists() && !taken.contains(&candidate) {
+        return candidate;
+    }
+
+    let stem = file_name
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = file_name
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+
+    (1..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| !candidate.exists() && !taken.contains(candidate))
+        .expect("unbounded range always yields a free name")
+}
+
+// One line of the undo journal
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum JournalEntry {
+    CreateDir {
+        path: PathBuf,
+    },
+    Move {
+        source: PathBuf,
+        destination: PathBuf,
+    },
+    Copy {
+        source: PathBuf,
+        destination: PathBuf,
+        // Missing in journals written before copies were stamped
+        #[serde(default)]
+        stamp: Option<FileStamp>,
+    },
+}
+
+// Size and modification time of a copy when it was made, so undo only
+// deletes copies nobody has edited since
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    size: u64,
+    modified: SystemTime,
+}
+
+impl FileStamp {
+    fn read(path: &Path) -> std::io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        Ok(Self {
+            size: metadata.len(),
+            modified: metadata.modified()?,
+        })
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FileFailure {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ApplyReport {
+    pub completed: usize,
+    pub failures: Vec<FileFailure>,
+}
+
+// Carry out a plan, appending each completed operation to the journal at
+// `journal_path` as it happens, so even an interrupted run can be undone.
+pub fn apply_plan(plan: &OrganizePlan, journal_path: &Path) -> Result<ApplyReport> {
+    let mut journal = Journal::create(journal_path)?;
+    let mut report = ApplyReport::default();
+
+    for file in &plan.files {
+        match apply_file(plan.mode, file, &mut journal) {
+            Ok(()) => report.completed += 1,
+            Err(e) => report.failures.push(FileFailure {
+                path: file.source.clone(),
+                error: format!("{:#}", e),
+            }),
+        }
+    }
+
+    Ok(report)
+}
+
+fn apply_file(mode: OrganizeMode, file: &PlannedFile, journal: &mut Journal) -> Result<()> {
+    if let Some(dir) = file.destination.parent() {
+        if !dir.exists() {
+            fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+            journal.append(&JournalEntry::CreateDir {
+                path: dir.to_path_buf(),
+            })?;
+        }
+    }
+
+    // Never overwrite, even if the file appeared after planning
+    if file.destination.exists() {
+        anyhow::bail!("Destination already exists: {:?}", file.destination);
+    }
+
+    match mode {
+        OrganizeMode::Move => {
+            move_file(&file.source, &file.destination)?;
+            journal.append(&JournalEntry::Move {
+                source: file.source.clone(),
+                destination: file.destination.clone(),
+            })
+        }
+        OrganizeMode::Copy => {
+            fs::copy(&file.source, &file.destination).with_context(|| {
+                format!("Failed to copy {:?} to {:?}", file.source, file.destination)
+            })?;
+            let stamp = FileStamp::read(&file.destination)
+                .with_context(|| format!("Failed to read {:?}", file.destination))?;
+            journal.append(&JournalEntry::Copy {
+                source: file.source.clone(),
+                destination: file.destination.clone(),
+                stamp: Some(stamp),
+            })
+        }
+    }
+}
+
+// Rename, falling back to copy and delete across file systems
+fn move_file(source: &Path, destination: &Path) -> Result<()> {
+    if fs::rename(source, destination).is_ok() {
+        return Ok(());
+    }
+
+    fs::copy(source, destination)
+        .with_context(|| format!("Failed to move {:?} to {:?}", source, destination))?;
+    if let Err(e) = fs::remove_file(source) {
+        let _ = fs::remove_file(destination);
+        return Err(e).with_context(|| format!("Failed to move {:?}", source));
+    }
+    Ok(())
+}
+
+struct Journal {
+    file: File,
+}
+
+impl Journal {
+    fn create(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to create journal {:?}", path))?;
+        Ok(Self { file })
+    }
+
+    fn append(&mut self, entry: &JournalEntry) -> Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        self.file.sync_data()?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct UndoReport {
+    pub restored: usize,
+    pub failures: Vec<FileFailure>,
+}
+
+// Revert the operations recorded in a journal, newest first. Folders created
+// by the run are removed once empty. Files changed since are left alone.
+// Files already back in place, e.g. from an earlier partial undo, count as
+// restored, so an undo can be retried with the same journal.
+pub fn undo(journal_path: &Path) -> Result<UndoReport> {
+    let file = File::open(journal_path)
+        .with_context(|| format!("Failed to open journal {:?}", journal_path))?;
+
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<JournalEntry>(&line) {
+            Ok(entry) => entries.push(entry),
+            // A crash can leave a partial last line
+            Err(e) => warn!("Ignoring unreadable journal line: {}", e),
+        }
+    }
+
+    let mut report = UndoReport::default();
+    for entry in entries.iter().rev() {
+        let (path, undone) = match entry {
+            JournalEntry::CreateDir { path } => {
+                // Only succeeds once empty, which leaves user files in place
+                let _ = fs::remove_dir(path);
+                continue;
+            }
+            JournalEntry::Move {
+                source,
+                destination,
+            } => (source, undo_move(source, destination)),
+            JournalEntry::Copy {
+                source,
+                destination,
+                stamp,
+            } => (source, undo_copy(destination, *stamp)),
+        };
+
+        match undone {
+            Ok(()) => report.restored += 1,
+            Err(e) => report.failures.push(FileFailure {
+                path: path.clone(),
+                error: format!("{:#}", e),
+            }),
+        }
+    }
+
+    Ok(report)
+}
+
+fn undo_move(source: &Path, destination: &Path) -> Result<()> {
+    if source.exists() && !destination.exists() {
+        return Ok(());
+    }
+    if source.exists() {
+        anyhow::bail!("Original location is occupied again: {:?}", source);
+    }
+    if !destination.exists() {
+        anyhow::bail!("Moved file is gone: {:?}", destination);
+    }
+    if let Some(dir) = source.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    move_file(destination, source)
+}
+
+fn undo_copy(destination: &Path, stamp: Option<FileStamp>) -> Result<()> {
+    let current = match FileStamp::read(destination) {
+        Ok(current) => current,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", destination)),
+    };
+    if stamp != Some(current) {
+        anyhow::bail!("Copy was changed since organizing: {:?}", destination);
+    }
+
+    fs::remove_file(destination).with_context(|| format!("Failed to remove {:?}", destination))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "taurivision-organize-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // Apply a plan sorting `files` (name, label) in `photos`, returning the journal path
+    fn organize(photos: &Path, mode: OrganizeMode, files: &[(&str, &str)]) -> PathBuf {
+        let items: Vec<OrganizeItem> = files
+            .iter()
+            .map(|(name, label)| {
+                fs::write(photos.join(name), name).unwrap();
+                OrganizeItem {
+                    path: photos.join(name),
+                    top: Some((label.to_string(), 0.9)),
+                }
+            })
+            .collect();
+        let plan = plan_organize(&items, photos, mode, None, 0.5);
+
+        let journal = photos.with_extension("jsonl");
+        let report = apply_plan(&plan, &journal).unwrap();
+        assert_eq!(report.completed, files.len());
+        journal
+    }
+
+    #[test]
+    fn retried_undo_finishes_a_partial_one() {
+        let root = temp_dir("retry");
+        let photos = root.join("photos");
+        fs::create_dir_all(&photos).unwrap();
+        let journal = organize(
+            &photos,
+            OrganizeMode::Move,
+            &[("a.jpg", "cat"), ("b.jpg", "dog")],
+        );
+
+        // A new file where b.jpg was blocks its restore
+        fs::write(photos.join("b.jpg"), "new").unwrap();
+        let first = undo(&journal).unwrap();
+        assert_eq!(first.restored, 1);
+        assert_eq!(first.failures.len(), 1);
+        assert_eq!(first.failures[0].path, photos.join("b.jpg"));
+        assert_eq!(fs::read(photos.join("a.jpg")).unwrap(), b"a.jpg");
+
+        fs::remove_file(photos.join("b.jpg")).unwrap();
+        let retry = undo(&journal).unwrap();
+        assert_eq!(retry.restored, 2);
+        assert!(retry.failures.is_empty());
+        assert_eq!(fs::read(photos.join("a.jpg")).unwrap(), b"a.jpg");
+        assert_eq!(fs::read(photos.join("b.jpg")).unwrap(), b"b.jpg");
+        assert!(!photos.join("cat").exists());
+        assert!(!photos.join("dog").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn undo_keeps_copies_changed_since() {
+        let root = temp_dir("copies");
+        let photos = root.join("photos");
+        fs::create_dir_all(&photos).unwrap();
+        let journal = organize(
+            &photos,
+            OrganizeMode::Copy,
+            &[("a.jpg", "cat"), ("b.jpg", "cat")],
+        );
+
+        fs::write(photos.join("cat/b.jpg"), "edited copy").unwrap();
+        let report = undo(&journal).unwrap();
+        assert_eq!(report.restored, 1);
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].path, photos.join("b.jpg"));
+
+        assert!(!photos.join("cat/a.jpg").exists());
+        assert_eq!(fs::read(photos.join("cat/b.jpg")).unwrap(), b"edited copy");
+        assert_eq!(fs::read(photos.join("a.jpg")).unwrap(), b"a.jpg");
+        assert_eq!(fs::read(photos.join("b.jpg")).unwrap(), b"b.jpg");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
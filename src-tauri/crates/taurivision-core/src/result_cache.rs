@@ -0,0 +1,288 @@
+use crate::hash::to_hex;
+use crate::model_manager::ModelInfo;
+use crate::RecognitionResult;
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use lru::LruCache;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+
+// Identifies one recognition: the same image bytes run through the same
+// model file with the same preprocessing always give the same results
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey(String);
+
+impl CacheKey {
+    pub fn new(content_hash: &str, model: &ModelInfo, profile: &str, top_k: usize) -> Self {
+        let mut hasher = Sha256::new();
+        let top_k = top_k.to_string();
+        for part in [
+            content_hash,
+            &model.id,
+            &model.sha256,
+            profile,
+            top_k.as_str(),
+        ] {
+            hasher.update(part.as_bytes());
+            // Separator, so adjacent parts cannot run into each other
+            hasher.update([0]);
+        }
+
+        Self(to_hex(&hasher.finalize()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct CacheStats {
+    pub memory_hits: u64,
+    pub disk_hits: u64,
+    pub misses: u64,
+    pub memory_entries: usize,
+    pub memory_capacity: usize,
+    pub disk_enabled: bool,
+    pub disk_entries: usize,
+}
+
+// Recognition results keyed by `CacheKey`, with an in-memory LRU tier
+// and an optional on-disk tier that survives restarts. Both tiers hold at
+// most `memory_capacity` entries; the disk tier evicts the oldest written.
+pub struct ResultCache {
+    memory: LruCache<CacheKey, Vec<RecognitionResult>>,
+    disk_dir: Option<PathBuf>,
+    // Keys on disk, most recently written first
+    disk_entries: LruCache<CacheKey, ()>,
+    memory_hits: u64,
+    disk_hits: u64,
+    misses: u64,
+}
+
+impl ResultCache {
+    pub fn new(memory_capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(memory_capacity).unwrap_or(NonZeroUsize::MIN);
+
+        Self {
+            memory: LruCache::new(capacity),
+            disk_dir: None,
+            disk_entries: LruCache::new(capacity),
+            memory_hits: 0,
+            disk_hits: 0,
+            misses: 0,
+        }
+    }
+
+    // Also persist entries as JSON files in `dir`
+    pub fn enable_disk_tier(&mut self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create result cache dir: {:?}", dir))?;
+
+        // Pick up entries from earlier runs, oldest first by write time
+        let mut existing = Vec::new();
+        for entry in fs::read_dir(dir)
+            .with_context(|| format!("Failed to read result cache dir: {:?}", dir))?
+        {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(key) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let modified = fs::metadata(&path).and_then(|metadata| metadata.modified())?;
+            existing.push((modified, CacheKey(key.to_string())));
+        }
+        existing.sort_by_key(|(modified, _)| *modified);
+
+        self.disk_dir = Some(dir.to_path_buf());
+        self.disk_entries.clear();
+        for (_, key) in existing {
+            self.track_disk_entry(key);
+        }
+        Ok(())
+    }
+
+    pub fn get(&mut self, key: &CacheKey) -> Option<Vec<RecognitionResult>> {
+        if let Some(results) = self.memory.get(key) {
+            self.memory_hits += 1;
+            return Some(results.clone());
+        }
+
+        if let Some(results) = self.read_disk(key) {
+            self.disk_hits += 1;
+            self.memory.put(key.clone(), results.clone());
+            return Some(results);
+        }
+
+        self.misses += 1;
+        None
+    }
+
+    pub fn insert(&mut self, key: &CacheKey, results: &[RecognitionResult]) {
+        self.memory.put(key.clone(), results.to_vec());
+
+        // The disk tier is best effort, the memory tier still serves the entry
+        if let Err(e) = self.write_disk(key, results) {
+            warn!("Failed to write result cache entry: {:#}", e);
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            memory_hits: self.memory_hits,
+            disk_hits: self.disk_hits,
+            misses: self.misses,
+            memory_entries: self.memory.len(),
+            memory_capacity: self.memory.cap().get(),
+            disk_enabled: self.disk_dir.is_some(),
+            disk_entries: self.disk_entries.len(),
+        }
+    }
+
+    fn disk_path(&self, key: &CacheKey) -> Option<PathBuf> {
+        self.disk_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.json", key.as_str())))
+    }
+
+    fn read_disk(&mut self, key: &CacheKey) -> Option<Vec<RecognitionResult>> {
+        let path = self.disk_path(key)?;
+        let contents = fs::read(&path).ok()?;
+
+        match serde_json::from_slice(&contents) {
+            Ok(results) => Some(results),
+            Err(e) => {
+                // A corrupt entry is dropped and recomputed
+                debug!("Discarding unreadable cache entry {:?}: {}", path, e);
+                let _ = fs::remove_file(&path);
+                self.disk_entries.pop(key);
+                None
+            }
+        }
+    }
+
+    fn write_disk(&mut self, key: &CacheKey, results: &[RecognitionResult]) -> Result<()> {
+        let Some(path) = self.disk_path(key) else {
+            return Ok(());
+        };
+
+        // Write then rename, so readers never see a partial entry
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec(results)?)
+            .with_context(|| format!("Failed to write {:?}", tmp_path))?;
+        fs::rename(&tmp_path, &path).with_context(|| format!("Failed to write {:?}", path))?;
+
+        self.track_disk_entry(key.clone());
+        Ok(())
+    }
+
+    // Record a key as written to disk, deleting the oldest entry when full
+    fn track_disk_entry(&mut self, key: CacheKey) {
+        let Some((evicted, ())) = self.disk_entries.push(key.clone(), ()) else {
+            return;
+        };
+        // `push` hands back the key itself when it was already present
+        if evicted == key {
+            return;
+        }
+        if let Some(path) = self.disk_path(&evicted) {
+            if let Err(e) = fs::remove_file(&path) {
+                debug!("Failed to evict cache entry {:?}: {}", path, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "taurivision-result-cache-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn key(name: &str) -> CacheKey {
+        CacheKey(name.to_string())
+    }
+
+    fn results(label: &str) -> Vec<RecognitionResult> {
+        vec![RecognitionResult {
+            label: label.to_string(),
+            confidence: 0.75,
+        }]
+    }
+
+    #[test]
+    fn disk_tier_survives_restart() {
+        let dir = temp_dir("restart");
+
+        let mut cache = ResultCache::new(4);
+        cache.enable_disk_tier(&dir).unwrap();
+        cache.insert(&key("a"), &results("cat"));
+
+        let mut reopened = ResultCache::new(4);
+        reopened.enable_disk_tier(&dir).unwrap();
+        let cached = reopened.get(&key("a")).unwrap();
+        assert_eq!(cached[0].label, "cat");
+        assert_eq!(cached[0].confidence, 0.75);
+
+        let stats = reopened.stats();
+        assert_eq!((stats.disk_hits, stats.memory_hits), (1, 0));
+        // Served from memory after the first disk hit
+        reopened.get(&key("a")).unwrap();
+        assert_eq!(reopened.stats().memory_hits, 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn disk_tier_evicts_oldest_entry() {
+        let dir = temp_dir("evict");
+
+        let mut cache = ResultCache::new(2);
+        cache.enable_disk_tier(&dir).unwrap();
+        cache.insert(&key("a"), &results("cat"));
+        cache.insert(&key("b"), &results("dog"));
+        cache.insert(&key("c"), &results("fish"));
+
+        assert!(!dir.join("a.json").exists());
+        assert!(dir.join("b.json").exists());
+        assert!(dir.join("c.json").exists());
+        assert_eq!(cache.stats().disk_entries, 2);
+
+        // A smaller cache trims what earlier runs left behind
+        let mut reopened = ResultCache::new(1);
+        reopened.enable_disk_tier(&dir).unwrap();
+        assert_eq!(reopened.stats().disk_entries, 1);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt_entries_are_dropped() {
+        let dir = temp_dir("corrupt");
+
+        let mut cache = ResultCache::new(2);
+        cache.enable_disk_tier(&dir).unwrap();
+        fs::write(dir.join("a.json"), b"not json").unwrap();
+        cache.enable_disk_tier(&dir).unwrap();
+
+        assert!(cache.get(&key("a")).is_none());
+        assert!(!dir.join("a.json").exists());
+        assert_eq!(cache.stats().disk_entries, 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
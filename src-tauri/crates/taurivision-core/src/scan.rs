@@ -0,0 +1,39 @@
+// Finding image files on disk
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "bmp", "gif", "webp", "tif", "tiff"];
+
+// Image files in `dir`, sorted, optionally including subdirectories
+pub fn scan_dir(dir: &Path, recursive: bool) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    collect_dir(dir, recursive, &mut paths)?;
+    paths.sort();
+    Ok(paths)
+}
+
+pub fn collect_dir(dir: &Path, recursive: bool, paths: &mut Vec<PathBuf>) -> Result<()> {
+    let entries =
+        fs::read_dir(dir).with_context(|| format!("Failed to read directory {:?}", dir))?;
+
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            if recursive {
+                collect_dir(&path, recursive, paths)?;
+            }
+        } else if is_image(&path) {
+            paths.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
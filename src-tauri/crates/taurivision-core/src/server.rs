@@ -0,0 +1,333 @@
+use crate::image_processor::{ImageInputError, ImageProcessor};
+use crate::model_manager::{ModelError, ModelInfo, ModelManager};
+use crate::RecognitionResult;
+use anyhow::{Context, Result};
+use axum::body::Bytes;
+use axum::extract::{DefaultBodyLimit, FromRequest, Multipart, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub bind_addr: SocketAddr,
+    // Upper bound for a whole request body, single image or batch
+    pub max_body_bytes: usize,
+    pub max_batch_size: usize,
+    pub default_top_k: usize,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind_addr: SocketAddr::from(([127, 0, 0, 1], 8080)),
+            max_body_bytes: 10 * 1024 * 1024,
+            max_batch_size: 16,
+            default_top_k: 5,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct ServerState {
+    model_manager: Arc<ModelManager>,
+    image_processor: Arc<ImageProcessor>,
+    config: Arc<ServerConfig>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ClassifyParams {
+    model: Option<String>,
+    top_k: Option<usize>,
+}
+
+#[derive(Serialize, Debug)]
+struct ClassifyResponse {
+    model: String,
+    results: Vec<RecognitionResult>,
+    elapsed_ms: f64,
+}
+
+#[derive(Serialize, Debug)]
+struct BatchItem {
+    name: Option<String>,
+    results: Option<Vec<RecognitionResult>>,
+    error: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct BatchResponse {
+    model: String,
+    items: Vec<BatchItem>,
+    elapsed_ms: f64,
+}
+
+#[derive(Serialize, Debug)]
+struct ModelsResponse {
+    default_model: Option<String>,
+    models: Vec<ModelInfo>,
+}
+
+#[derive(Serialize, Debug)]
+struct HealthResponse {
+    status: &'static str,
+    models_loaded: usize,
+}
+
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        if let Some(
+            ImageInputError::TooLarge { .. }
+            | ImageInputError::TooManyBytes { .. }
+            | ImageInputError::DimensionsTooLarge { .. },
+        ) = error.downcast_ref::<ImageInputError>()
+        {
+            return Self::new(StatusCode::PAYLOAD_TOO_LARGE, format!("{:#}", error));
+        }
+
+        let status = match error.downcast_ref::<ModelError>() {
+            Some(ModelError::UnknownModel(_)) => StatusCode::NOT_FOUND,
+            Some(ModelError::NotInitialized) => StatusCode::SERVICE_UNAVAILABLE,
+            Some(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            // Anything else comes from decoding or preprocessing the upload
+            None => StatusCode::UNPROCESSABLE_ENTITY,
+        };
+
+        Self::new(status, format!("{:#}", error))
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = Json(serde_json::json!({ "error": self.message }));
+        (self.status, body).into_response()
+    }
+}
+
+pub fn router(model_manager: ModelManager, config: ServerConfig) -> Router {
+    let max_body_bytes = config.max_body_bytes;
+    let state = ServerState {
+        model_manager: Arc::new(model_manager),
+        image_processor: Arc::new(ImageProcessor::new()),
+        config: Arc::new(config),
+    };
+
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/v1/models", get(list_models))
+        .route("/v1/classify", post(classify))
+        .route("/v1/classify/batch", post(classify_batch))
+        .layer(DefaultBodyLimit::max(max_body_bytes))
+        .with_state(state)
+}
+
+pub async fn serve(model_manager: ModelManager, config: ServerConfig) -> Result<()> {
+    let bind_addr = config.bind_addr;
+    let app = router(model_manager, config);
+
+    let listener = tokio::net::TcpListener::bind(bind_addr)
+        .await
+        .with_context(|| format!("Failed to bind to {}", bind_addr))?;
+
+    info!("Inference server listening on {}", bind_addr);
+    axum::serve(listener, app)
+        .await
+        .context("Inference server failed")
+}
+
+async fn healthz(State(state): State<ServerState>) -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: "ok",
+        models_loaded: state.model_manager.models().len(),
+    })
+}
+
+async fn list_models(State(state): State<ServerState>) -> Json<ModelsResponse> {
+    Json(ModelsResponse {
+        default_model: state.model_manager.default_model_id().map(str::to_string),
+        models: state.model_manager.models(),
+    })
+}
+
+async fn classify(
+    State(state): State<ServerState>,
+    Query(params): Query<ClassifyParams>,
+    request: Request,
+) -> Result<Json<ClassifyResponse>, ApiError> {
+    let start = Instant::now();
+    let model_id = resolve_model(&state, &params)?;
+    let top_k = params.top_k.unwrap_or(state.config.default_top_k);
+
+    let mut images = read_images(&state, request).await?;
+    if images.len() != 1 {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("Expected exactly one image, got {}", images.len()),
+        ));
+    }
+    let (_, image) = images.remove(0);
+
+    let results = run_classification(&state, &model_id, top_k, image).await?;
+
+    Ok(Json(ClassifyResponse {
+        model: model_id,
+        results,
+        elapsed_ms: start.elapsed().as_secs_f64() * 1000.0,
+    }))
+}
+
+async fn classify_batch(
+    State(state): State<ServerState>,
+    Query(params): Query<ClassifyParams>,
+    request: Request,
+) -> Result<Json<BatchResponse>, ApiError> {
+    let start = Instant::now();
+    let model_id = resolve_model(&state, &params)?;
+    let top_k = params.top_k.unwrap_or(state.config.default_top_k);
+
+    if !is_multipart(&request) {
+        return Err(ApiError::new(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "Batch requests must be multipart/form-data",
+        ));
+    }
+
+    let images = read_images(&state, request).await?;
+    let mut items = Vec::with_capacity(images.len());
+
+    // One bad image should not fail the whole batch
+    for (name, image) in images {
+        let item = match run_classification(&state, &model_id, top_k, image).await {
+            Ok(results) => BatchItem {
+                name,
+                results: Some(results),
+                error: None,
+            },
+            Err(e) => BatchItem {
+                name,
+                results: None,
+                error: Some(e.message),
+            },
+        };
+        items.push(item);
+    }
+
+    Ok(Json(BatchResponse {
+        model: model_id,
+        items,
+        elapsed_ms: start.elapsed().as_secs_f64() * 1000.0,
+    }))
+}
+
+fn resolve_model(state: &ServerState, params: &ClassifyParams) -> Result<String, ApiError> {
+    let model_id = match params.model.as_deref() {
+        Some(model_id) => model_id,
+        None => state
+            .model_manager
+            .default_model_id()
+            .ok_or_else(|| anyhow::Error::from(ModelError::NotInitialized))?,
+    };
+
+    if !state.model_manager.has_model(model_id) {
+        return Err(anyhow::Error::from(ModelError::UnknownModel(model_id.to_string())).into());
+    }
+
+    Ok(model_id.to_string())
+}
+
+fn is_multipart(request: &Request) -> bool {
+    request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.starts_with("multipart/form-data"))
+        .unwrap_or(false)
+}
+
+// Collect images from a multipart body (one per field) or a raw image body
+async fn read_images(
+    state: &ServerState,
+    request: Request,
+) -> Result<Vec<(Option<String>, Bytes)>, ApiError> {
+    if !is_multipart(&request) {
+        let body = axum::body::to_bytes(request.into_body(), state.config.max_body_bytes)
+            .await
+            .map_err(|_| ApiError::new(StatusCode::PAYLOAD_TOO_LARGE, "Request body too large"))?;
+        return Ok(vec![(None, body)]);
+    }
+
+    let mut multipart = Multipart::from_request(request, state)
+        .await
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    let mut images = Vec::new();
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e.to_string()))?
+    {
+        if images.len() == state.config.max_batch_size {
+            return Err(ApiError::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("At most {} images per request", state.config.max_batch_size),
+            ));
+        }
+
+        let name = field
+            .file_name()
+            .or_else(|| field.name())
+            .map(str::to_string);
+        let data = field
+            .bytes()
+            .await
+            .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e.to_string()))?;
+        images.push((name, data));
+    }
+
+    Ok(images)
+}
+
+async fn run_classification(
+    state: &ServerState,
+    model_id: &str,
+    top_k: usize,
+    image: Bytes,
+) -> Result<Vec<RecognitionResult>, ApiError> {
+    let state = state.clone();
+    let model_id = model_id.to_string();
+
+    // Decoding and inference are CPU bound, keep them off the async workers
+    let results = tokio::task::spawn_blocking(move || {
+        let image_data = state.image_processor.process_bytes(&image)?;
+        state
+            .model_manager
+            .recognize_with(&model_id, &image_data, top_k)
+    })
+    .await
+    .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))??;
+
+    Ok(results
+        .into_iter()
+        .map(|(label, confidence)| RecognitionResult { label, confidence })
+        .collect())
+}
@@ -0,0 +1,523 @@
+// Writing predicted labels as keywords into XMP metadata, either a sidecar
+// next to the image or a packet embedded in a JPEG. Keywords go into
+// dc:subject, the property IPTC Core uses for keywords.
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+// Namespace header that marks a JPEG APP1 segment as XMP
+const XMP_APP1_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+// A segment length is a u16 that includes its own two bytes
+const MAX_APP1_PAYLOAD: usize = u16::MAX as usize - 2;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TagTarget {
+    // `photo.jpg.xmp` next to `photo.jpg`
+    #[default]
+    Sidecar,
+    // Inside the JPEG itself; other formats fall back to a sidecar
+    Embedded,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TagAction {
+    Create,
+    Update,
+    // Every keyword is already present
+    Unchanged,
+}
+
+// What tagging one image would do, computed without writing anything
+#[derive(Serialize, Debug, Clone)]
+pub struct TagPlan {
+    pub image: PathBuf,
+    pub target: TagTarget,
+    // File that would be written: the sidecar or the image itself
+    pub file: PathBuf,
+    pub keywords: Vec<String>,
+    pub action: TagAction,
+    // An existing file (the original image or a sidecar) would be rewritten
+    pub modifies_existing: bool,
+}
+
+// Keeps the image's extension, so photo.jpg and photo.png get separate sidecars
+pub fn sidecar_path(image: &Path) -> PathBuf {
+    let mut path = image.as_os_str().to_owned();
+    path.push(".xmp");
+    PathBuf::from(path)
+}
+
+pub fn plan_tags(image: &Path, keywords: &[String], target: TagTarget) -> Result<TagPlan> {
+    let target = if target == TagTarget::Embedded && !is_jpeg(image) {
+        TagTarget::Sidecar
+    } else {
+        target
+    };
+    let file = match target {
+        TagTarget::Sidecar => sidecar_path(image),
+        TagTarget::Embedded => image.to_path_buf(),
+    };
+
+    let exists = file.exists();
+    let action = match render(&file, target, keywords)? {
+        None => TagAction::Unchanged,
+        Some(_) if exists => TagAction::Update,
+        Some(_) => TagAction::Create,
+    };
+
+    Ok(TagPlan {
+        image: image.to_path_buf(),
+        target,
+        file,
+        keywords: keywords.to_vec(),
+        action,
+        modifies_existing: exists && action != TagAction::Unchanged,
+    })
+}
+
+// Carry out a plan. The file is re-read, so keywords added since planning are kept.
+// Returns false if there was nothing to write.
+pub fn apply_tags(plan: &TagPlan) -> Result<bool> {
+    let Some(contents) = render(&plan.file, plan.target, &plan.keywords)? else {
+        return Ok(false);
+    };
+
+    write_replacing(&plan.file, &contents)?;
+    Ok(true)
+}
+
+// New contents of `file` with `keywords` added, None if nothing would change
+fn render(file: &Path, target: TagTarget, keywords: &[String]) -> Result<Option<Vec<u8>>> {
+    if keywords.is_empty() {
+        return Ok(None);
+    }
+
+    match target {
+        TagTarget::Sidecar => {
+            let packet = match fs::read_to_string(file) {
+                Ok(xmp) => merge_keywords(&xmp, keywords)
+                    .with_context(|| format!("Failed to update sidecar {:?}", file))?,
+                Err(e) if e.kind() == ErrorKind::NotFound => Some(new_packet(keywords)),
+                Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", file)),
+            };
+            Ok(packet.map(String::into_bytes))
+        }
+        TagTarget::Embedded => {
+            let jpeg = fs::read(file).with_context(|| format!("Failed to read {:?}", file))?;
+            embed_keywords(&jpeg, keywords).with_context(|| format!("Failed to tag {:?}", file))
+        }
+    }
+}
+
+fn is_jpeg(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| matches!(ext.to_ascii_lowercase().as_str(), "jpg" | "jpeg"))
+        .unwrap_or(false)
+}
+
+// Insert or update the XMP APP1 segment of a JPEG
+fn embed_keywords(jpeg: &[u8], keywords: &[String]) -> Result<Option<Vec<u8>>> {
+    if !jpeg.starts_with(&[0xFF, 0xD8]) {
+        bail!("Not a JPEG file");
+    }
+
+    // Walk the header segments up to the image data
+    let mut pos = 2;
+    let mut insert_at = 2;
+    let mut existing = None;
+    loop {
+        if pos + 2 > jpeg.len() || jpeg[pos] != 0xFF {
+            bail!("Malformed JPEG segment at offset {}", pos);
+        }
+        let marker = jpeg[pos + 1];
+        if marker == 0xFF {
+            // Fill byte before a marker
+            pos += 1;
+            continue;
+        }
+        // Start of scan or end of image: no more metadata segments
+        if marker == 0xDA || marker == 0xD9 {
+            break;
+        }
+
+        if pos + 4 > jpeg.len() {
+            bail!("Truncated JPEG segment at offset {}", pos);
+        }
+        let length = u16::from_be_bytes([jpeg[pos + 2], jpeg[pos + 3]]) as usize;
+        let end = pos + 2 + length;
+        if length < 2 || end > jpeg.len() {
+            bail!("Invalid JPEG segment length at offset {}", pos);
+        }
+
+        if marker == 0xE1 && jpeg[pos + 4..end].starts_with(XMP_APP1_HEADER) {
+            existing = Some((pos, end));
+        }
+        // New XMP goes after the leading JFIF / Exif segments
+        if matches!(marker, 0xE0 | 0xE1) && insert_at == pos {
+            insert_at = end;
+        }
+        pos = end;
+    }
+
+    let packet = match existing {
+        Some((start, end)) => {
+            let xmp = std::str::from_utf8(&jpeg[start + 4 + XMP_APP1_HEADER.len()..end])
+                .context("Embedded XMP is not valid UTF-8")?;
+            match merge_keywords(xmp, keywords)? {
+                Some(packet) => packet,
+                None => return Ok(None),
+            }
+        }
+        None => new_packet(keywords),
+    };
+
+    // Extended XMP split across several segments is not supported
+    let payload_len = XMP_APP1_HEADER.len() + packet.len();
+    if payload_len > MAX_APP1_PAYLOAD {
+        bail!(
+            "XMP packet of {} bytes does not fit in a JPEG segment",
+            payload_len
+        );
+    }
+
+    let (start, end) = existing.unwrap_or((insert_at, insert_at));
+    let mut output = Vec::with_capacity(jpeg.len() + payload_len + 4);
+    output.extend_from_slice(&jpeg[..start]);
+    output.extend_from_slice(&[0xFF, 0xE1]);
+    output.extend_from_slice(&((payload_len + 2) as u16).to_be_bytes());
+    output.extend_from_slice(XMP_APP1_HEADER);
+    output.extend_from_slice(packet.as_bytes());
+    output.extend_from_slice(&jpeg[end..]);
+
+    Ok(Some(output))
+}
+
+fn new_packet(keywords: &[String]) -> String {
+    format!(
+        "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n\
+         <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n\
+         \x20<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n\
+         {}\
+         \x20</rdf:RDF>\n\
+         </x:xmpmeta>\n\
+         <?xpacket end=\"w\"?>\n",
+        subject_description(keywords)
+    )
+}
+
+fn subject_description<S: AsRef<str>>(keywords: &[S]) -> String {
+    format!(
+        "  <rdf:Description rdf:about=\"\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n\
+         \x20  <dc:subject>\n\
+         \x20   <rdf:Bag>\n\
+         {}\
+         \x20   </rdf:Bag>\n\
+         \x20  </dc:subject>\n\
+         \x20 </rdf:Description>\n",
+        bag_items(keywords)
+    )
+}
+
+fn bag_items<S: AsRef<str>>(keywords: &[S]) -> String {
+    keywords
+        .iter()
+        .map(|keyword| format!("     <rdf:li>{}</rdf:li>\n", escape_xml(keyword.as_ref())))
+        .collect()
+}
+
+// Add keywords missing from an existing packet's dc:subject bag.
+// Returns None if every keyword is already there (compared case-insensitively).
+fn merge_keywords(xmp: &str, keywords: &[String]) -> Result<Option<String>> {
+    let subject = find_subject_bag(xmp)?;
+    let existing: Vec<String> = match subject {
+        Some((bag_start, bag_end)) => bag_keywords(&xmp[bag_start..bag_end]),
+        None => Vec::new(),
+    };
+
+    let mut added: Vec<&String> = Vec::new();
+    for keyword in keywords {
+        let present = existing
+            .iter()
+            .chain(added.iter().copied())
+            .any(|other| other.eq_ignore_ascii_case(keyword));
+        if !present {
+            added.push(keyword);
+        }
+    }
+    if added.is_empty() {
+        return Ok(None);
+    }
+
+    let mut merged = String::with_capacity(xmp.len() + added.len() * 32);
+    match subject {
+        Some((_, bag_end)) => {
+            // Keep the indentation of `</rdf:Bag>` when it sits on its own line
+            let line_start = xmp[..bag_end].trim_end_matches([' ', '\t']);
+            let insert_at = if line_start.ends_with('\n') {
+                line_start.len()
+            } else {
+                bag_end
+            };
+            merged.push_str(&xmp[..insert_at]);
+            merged.push_str(&bag_items(&added));
+            merged.push_str(&xmp[insert_at..]);
+        }
+        None => {
+            let Some(rdf_end) = xmp.rfind("</rdf:RDF>") else {
+                bail!("XMP packet has no rdf:RDF element");
+            };
+            merged.push_str(&xmp[..rdf_end]);
+            merged.push_str(&subject_description(&added));
+            merged.push_str(&xmp[rdf_end..]);
+        }
+    }
+
+    Ok(Some(merged))
+}
+
+// Byte range between `<rdf:Bag>` and `</rdf:Bag>` inside dc:subject
+fn find_subject_bag(xmp: &str) -> Result<Option<(usize, usize)>> {
+    let Some(subject_start) = xmp.find("<dc:subject") else {
+        return Ok(None);
+    };
+    let Some(subject_len) = xmp[subject_start..].find("</dc:subject>") else {
+        bail!("Unsupported dc:subject in existing XMP");
+    };
+    let subject = &xmp[subject_start..subject_start + subject_len];
+
+    let bag_open = subject.find("<rdf:Bag").and_then(|open| {
+        let close = subject[open..].find('>')?;
+        Some(open + close + 1)
+    });
+    let bag_close = subject.find("</rdf:Bag>");
+    match (bag_open, bag_close) {
+        (Some(open), Some(close)) if open <= close => {
+            Ok(Some((subject_start + open, subject_start + close)))
+        }
+        _ => bail!("Unsupported dc:subject in existing XMP"),
+    }
+}
+
+fn bag_keywords(bag: &str) -> Vec<String> {
+    let mut keywords = Vec::new();
+    let mut rest = bag;
+
+    while let Some(open) = rest.find("<rdf:li") {
+        let after_open = &rest[open..];
+        let (Some(tag_end), Some(close)) = (after_open.find('>'), after_open.find("</rdf:li>"))
+        else {
+            break;
+        };
+        if tag_end < close {
+            keywords.push(unescape_xml(after_open[tag_end + 1..close].trim()));
+        }
+        rest = &after_open[close + "</rdf:li>".len()..];
+    }
+
+    keywords
+}
+
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape_xml(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+// Replace `path` through a temporary file in the same directory, so a failed
+// write never leaves a truncated image or sidecar behind
+fn write_replacing(path: &Path, contents: &[u8]) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("Not a file path: {:?}", path))?;
+    let mut tmp_name = file_name.to_os_string();
+    tmp_name.push(".taurivision-tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    fs::write(&tmp_path, contents).with_context(|| format!("Failed to write {:?}", tmp_path))?;
+
+    if let Ok(metadata) = fs::metadata(path) {
+        let _ = fs::set_permissions(&tmp_path, metadata.permissions());
+    }
+
+    fs::rename(&tmp_path, path).with_context(|| format!("Failed to replace {:?}", path))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keywords(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "taurivision-tagging-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn sidecars_keep_the_image_extension() {
+        assert_eq!(
+            sidecar_path(Path::new("dir/photo.jpg")),
+            Path::new("dir/photo.jpg.xmp")
+        );
+        assert_ne!(
+            sidecar_path(Path::new("photo.jpg")),
+            sidecar_path(Path::new("photo.png"))
+        );
+    }
+
+    #[test]
+    fn merges_into_existing_subject_bag() {
+        let xmp = new_packet(&keywords(&["cat", "Tabby & co"]));
+
+        let merged = merge_keywords(&xmp, &keywords(&["CAT", "dog"]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            bag_keywords(&merged[..merged.find("</rdf:Bag>").unwrap()]),
+            keywords(&["cat", "Tabby & co", "dog"])
+        );
+
+        assert!(merge_keywords(&merged, &keywords(&["dog", "tabby & CO"]))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn adds_subject_to_packet_without_one() {
+        let xmp = "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n\
+                   <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n\
+                   <rdf:Description rdf:about=\"\" xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\" xmp:Rating=\"4\"/>\n\
+                   </rdf:RDF>\n\
+                   </x:xmpmeta>\n";
+
+        let merged = merge_keywords(xmp, &keywords(&["cat"])).unwrap().unwrap();
+        assert!(merged.contains("xmp:Rating=\"4\""));
+        let (start, end) = find_subject_bag(&merged).unwrap().unwrap();
+        assert_eq!(bag_keywords(&merged[start..end]), keywords(&["cat"]));
+    }
+
+    #[test]
+    fn updates_existing_sidecar_file() {
+        let dir = temp_dir("sidecar");
+        let image = dir.join("photo.jpg");
+        fs::write(&image, b"not read for sidecars").unwrap();
+        let sidecar = sidecar_path(&image);
+        fs::write(&sidecar, new_packet(&keywords(&["cat"]))).unwrap();
+
+        let plan = plan_tags(&image, &keywords(&["cat", "dog"]), TagTarget::Sidecar).unwrap();
+        assert_eq!(plan.file, sidecar);
+        assert_eq!(plan.action, TagAction::Update);
+        assert!(plan.modifies_existing);
+
+        assert!(apply_tags(&plan).unwrap());
+        let xmp = fs::read_to_string(&sidecar).unwrap();
+        let (start, end) = find_subject_bag(&xmp).unwrap().unwrap();
+        assert_eq!(bag_keywords(&xmp[start..end]), keywords(&["cat", "dog"]));
+
+        // The image itself is untouched and a second run has nothing to do
+        assert_eq!(fs::read(&image).unwrap(), b"not read for sidecars");
+        let again = plan_tags(&image, &keywords(&["dog"]), TagTarget::Sidecar).unwrap();
+        assert_eq!(again.action, TagAction::Unchanged);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn test_jpeg() -> Vec<u8> {
+        let image =
+            image::RgbImage::from_fn(16, 8, |x, y| image::Rgb([x as u8 * 16, y as u8 * 32, 128]));
+        let mut jpeg = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new(&mut jpeg)
+            .encode(image.as_raw(), 16, 8, image::ColorType::Rgb8)
+            .unwrap();
+        jpeg
+    }
+
+    // Offsets of the XMP APP1 segments in a JPEG
+    fn xmp_segments(jpeg: &[u8]) -> Vec<(usize, usize)> {
+        let mut segments = Vec::new();
+        let mut pos = 2;
+        while jpeg[pos + 1] != 0xDA {
+            let end = pos + 2 + u16::from_be_bytes([jpeg[pos + 2], jpeg[pos + 3]]) as usize;
+            if jpeg[pos + 1] == 0xE1 && jpeg[pos + 4..end].starts_with(XMP_APP1_HEADER) {
+                segments.push((pos + 4 + XMP_APP1_HEADER.len(), end));
+            }
+            pos = end;
+        }
+        segments
+    }
+
+    fn embedded_keywords(jpeg: &[u8]) -> Vec<String> {
+        let segments = xmp_segments(jpeg);
+        assert_eq!(segments.len(), 1);
+        let xmp = std::str::from_utf8(&jpeg[segments[0].0..segments[0].1]).unwrap();
+        let (start, end) = find_subject_bag(xmp).unwrap().unwrap();
+        bag_keywords(&xmp[start..end])
+    }
+
+    // Everything from the start of scan on
+    fn scan_data(jpeg: &[u8]) -> &[u8] {
+        let start = jpeg
+            .windows(2)
+            .position(|pair| pair == [0xFF, 0xDA])
+            .unwrap();
+        &jpeg[start..]
+    }
+
+    #[test]
+    fn embeds_keywords_in_jpeg() {
+        let jpeg = test_jpeg();
+        assert!(xmp_segments(&jpeg).is_empty());
+
+        let tagged = embed_keywords(&jpeg, &keywords(&["cat"])).unwrap().unwrap();
+        assert_eq!(embedded_keywords(&tagged), keywords(&["cat"]));
+        assert_eq!(scan_data(&tagged), scan_data(&jpeg));
+        assert_eq!(
+            image::load_from_memory(&tagged).unwrap().to_rgb8(),
+            image::load_from_memory(&jpeg).unwrap().to_rgb8()
+        );
+
+        // A second run updates the segment in place instead of adding one
+        let retagged = embed_keywords(&tagged, &keywords(&["dog", "cat"]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(embedded_keywords(&retagged), keywords(&["cat", "dog"]));
+        assert_eq!(scan_data(&retagged), scan_data(&jpeg));
+
+        assert!(embed_keywords(&retagged, &keywords(&["dog"]))
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,104 @@
+// Tiny hand-built ONNX models for tests. They take the usual 1x3x224x224
+// input and score each class with the mean of one color channel, so a solid
+// color image scores its red, green and blue values.
+use crate::model_manager::INPUT_SIZE;
+use prost::Message;
+use tract_onnx::pb::tensor_proto::DataType;
+use tract_onnx::pb::tensor_shape_proto::{dimension, Dimension};
+use tract_onnx::pb::{
+    type_proto, GraphProto, ModelProto, NodeProto, OperatorSetIdProto, TensorProto,
+    TensorShapeProto, TypeProto, ValueInfoProto,
+};
+
+pub const LABELS: [&str; 3] = ["red", "green", "blue"];
+
+pub fn float_model() -> Vec<u8> {
+    model(pooling_nodes("input"), Vec::new()).encode_to_vec()
+}
+
+// The input goes through a QuantizeLinear/DequantizeLinear pair first, so
+// scores are rounded to multiples of `scale`
+pub fn quantized_model(scale: f32) -> Vec<u8> {
+    let mut nodes = vec![
+        node("QuantizeLinear", &["input", "scale", "zero_point"], &["q"]),
+        node("DequantizeLinear", &["q", "scale", "zero_point"], &["dq"]),
+    ];
+    nodes.extend(pooling_nodes("dq"));
+    let initializers = vec![
+        float_tensor("scale", &[], &[scale]),
+        TensorProto {
+            name: "zero_point".to_string(),
+            data_type: DataType::Uint8 as i32,
+            int32_data: vec![0],
+            ..Default::default()
+        },
+    ];
+    model(nodes, initializers).encode_to_vec()
+}
+
+fn pooling_nodes(input: &str) -> Vec<NodeProto> {
+    vec![
+        node("GlobalAveragePool", &[input], &["pooled"]),
+        node("Flatten", &["pooled"], &["scores"]),
+    ]
+}
+
+pub fn model(nodes: Vec<NodeProto>, initializers: Vec<TensorProto>) -> ModelProto {
+    let size = INPUT_SIZE as i64;
+    ModelProto {
+        ir_version: 7,
+        opset_import: vec![OperatorSetIdProto {
+            domain: String::new(),
+            version: 13,
+        }],
+        graph: Some(GraphProto {
+            name: "test".to_string(),
+            node: nodes,
+            initializer: initializers,
+            input: vec![value_info("input", &[1, 3, size, size])],
+            output: vec![value_info("scores", &[1, LABELS.len() as i64])],
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+pub fn node(op_type: &str, inputs: &[&str], outputs: &[&str]) -> NodeProto {
+    NodeProto {
+        op_type: op_type.to_string(),
+        input: inputs.iter().map(|name| name.to_string()).collect(),
+        output: outputs.iter().map(|name| name.to_string()).collect(),
+        ..Default::default()
+    }
+}
+
+pub fn float_tensor(name: &str, dims: &[i64], values: &[f32]) -> TensorProto {
+    TensorProto {
+        name: name.to_string(),
+        dims: dims.to_vec(),
+        data_type: DataType::Float as i32,
+        float_data: values.to_vec(),
+        ..Default::default()
+    }
+}
+
+fn value_info(name: &str, shape: &[i64]) -> ValueInfoProto {
+    let dim = shape
+        .iter()
+        .map(|&size| Dimension {
+            value: Some(dimension::Value::DimValue(size)),
+            ..Default::default()
+        })
+        .collect();
+    ValueInfoProto {
+        name: name.to_string(),
+        r#type: Some(TypeProto {
+            value: Some(type_proto::Value::TensorType(type_proto::Tensor {
+                elem_type: DataType::Float as i32,
+                shape: Some(TensorShapeProto { dim }),
+            })),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
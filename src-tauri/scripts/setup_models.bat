@@ -21,6 +21,28 @@ if %ERRORLEVEL% neq 0 (
     exit /b 1
 )
 
+REM Check the download against the committed known-good checksum, which the
+REM app verifies again before loading the model
+echo Verifying model checksum...
+if not exist "assets\model\mobilenet_v2.onnx.sha256" (
+    echo Missing known-good checksum assets\model\mobilenet_v2.onnx.sha256.
+    exit /b 1
+)
+set /p EXPECTED=<assets\model\mobilenet_v2.onnx.sha256
+for /f "tokens=1" %%h in ("!EXPECTED!") do set EXPECTED=%%h
+set ACTUAL=
+for /f "skip=1 tokens=*" %%h in ('certutil -hashfile assets\model\mobilenet_v2.onnx SHA256') do (
+    if not defined ACTUAL set ACTUAL=%%h
+)
+set ACTUAL=!ACTUAL: =!
+if /i not "!ACTUAL!"=="!EXPECTED!" (
+    echo Downloaded model does not match the known-good checksum.
+    echo   expected !EXPECTED!
+    echo   actual   !ACTUAL!
+    del assets\model\mobilenet_v2.onnx
+    exit /b 1
+)
+
 REM Download ImageNet labels
 echo Downloading ImageNet labels...
 curl -L -o assets\model\labels_temp.txt https://raw.githubusercontent.com/pytorch/hub/master/imagenet_classes.txt
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use taurivision_core::tagging::{self, TagAction, TagPlan, TagTarget};
+use taurivision_core::RecognitionResult;
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TagOptions {
+    pub target: TagTarget,
+    // Labels below this confidence are not written
+    pub min_confidence: f32,
+    pub max_keywords: usize,
+    // Descend into subfolders of the given folders
+    pub recursive: bool,
+    // Only report what would be written
+    pub dry_run: bool,
+    // Allow rewriting files that already exist: the original JPEG or an existing sidecar
+    pub confirm_overwrite: bool,
+}
+
+impl Default for TagOptions {
+    fn default() -> Self {
+        Self {
+            target: TagTarget::Sidecar,
+            min_confidence: 0.3,
+            max_keywords: 3,
+            recursive: false,
+            dry_run: true,
+            confirm_overwrite: false,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TagStatus {
+    // Dry run: the plan describes what would be written
+    Planned,
+    Written,
+    Unchanged,
+    // No label reached the confidence threshold
+    NoKeywords,
+    // Would rewrite an existing file without `confirm_overwrite`
+    NeedsConfirmation,
+    Failed,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TagReport {
+    pub path: String,
+    pub status: TagStatus,
+    pub plan: Option<TagPlan>,
+    pub error: Option<String>,
+}
+
+impl TagReport {
+    pub fn failed(path: &Path, error: String) -> Self {
+        Self {
+            path: path.to_string_lossy().into_owned(),
+            status: TagStatus::Failed,
+            plan: None,
+            error: Some(error),
+        }
+    }
+}
+
+// Top labels at or above the threshold, best first
+pub fn select_keywords(results: &[RecognitionResult], options: &TagOptions) -> Vec<String> {
+    results
+        .iter()
+        .filter(|result| result.confidence >= options.min_confidence)
+        .take(options.max_keywords)
+        .map(|result| result.label.clone())
+        .collect()
+}
+
+// Plan tagging one image and, unless this is a dry run, carry it out
+pub fn tag_image(path: &Path, results: &[RecognitionResult], options: &TagOptions) -> TagReport {
+    let keywords = select_keywords(results, options);
+    if keywords.is_empty() {
+        return TagReport {
+            path: path.to_string_lossy().into_owned(),
+            status: TagStatus::NoKeywords,
+            plan: None,
+            error: None,
+        };
+    }
+
+    let plan = match tagging::plan_tags(path, &keywords, options.target) {
+        Ok(plan) => plan,
+        Err(e) => return TagReport::failed(path, format!("{:#}", e)),
+    };
+
+    let (status, error) = if plan.action == TagAction::Unchanged {
+        (TagStatus::Unchanged, None)
+    } else if options.dry_run {
+        (TagStatus::Planned, None)
+    } else if plan.modifies_existing && !options.confirm_overwrite {
+        (TagStatus::NeedsConfirmation, None)
+    } else {
+        match tagging::apply_tags(&plan) {
+            Ok(true) => (TagStatus::Written, None),
+            Ok(false) => (TagStatus::Unchanged, None),
+            Err(e) => (TagStatus::Failed, Some(format!("{:#}", e))),
+        }
+    };
+
+    TagReport {
+        path: path.to_string_lossy().into_owned(),
+        status,
+        plan: Some(plan),
+        error,
+    }
+}
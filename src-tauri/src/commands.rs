@@ -0,0 +1,988 @@
+use crate::auto_tag::{self, TagOptions, TagReport};
+use crate::content_uri;
+use crate::export::{self, ExportFormat, ModelLabels};
+use crate::history::{HistoryPage, HistoryStore, NewHistoryEntry, SourceKind};
+use crate::model_store;
+use crate::model_watch::{self, ModelWatch};
+use crate::organizer::{self, AppliedOrganize, OrganizeOptions, OrganizeResult};
+use crate::path_scope;
+use anyhow::Context;
+use base64::{engine::general_purpose, Engine as _};
+use log::{debug, info, warn};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::ipc::InvokeBody;
+use tauri::{AppHandle, Manager, Runtime};
+use taurivision_core::benchmark::{self, BenchmarkConfig, BenchmarkReport};
+use taurivision_core::hash::sha256_hex;
+use taurivision_core::model_compare::{self, AbReport, CompareOptions};
+use taurivision_core::model_inspect::{self, InspectionReport};
+use taurivision_core::organize::{self, CategoryMap, FileFailure, OrganizeItem, UndoReport};
+use taurivision_core::{
+    thumbnail_jpeg, CacheKey, CacheStats, DynamicImage, EnsembleConfig, ImageProcessor, ModelCache,
+    ModelError, ModelInfo, ModelManager, RecognitionResult, ResultCache, DEFAULT_TOP_K,
+};
+use tokio::sync::Mutex;
+
+// Longest side of the thumbnail stored with each history entry
+const THUMBNAIL_SIZE: u32 = 160;
+
+// History page size when the frontend does not ask for one
+const DEFAULT_HISTORY_PAGE_SIZE: u32 = 50;
+
+// Results kept in memory, on top of the on-disk cache in the app cache dir
+const RESULT_CACHE_CAPACITY: usize = 512;
+
+// Picks the model for `recognize_image_bytes`, whose raw body carries no arguments
+const MODEL_ID_HEADER: &str = "model-id";
+
+// Define app state for use with Tauri commands
+pub struct AppState {
+    model_manager: Arc<Mutex<ModelManager>>,
+    image_processor: Arc<Mutex<ImageProcessor>>,
+    result_cache: Arc<Mutex<ResultCache>>,
+    // Hot reload of one model, off unless asked for
+    model_watch: Mutex<Option<ModelWatch>>,
+}
+
+impl AppState {
+    pub fn new(result_cache: ResultCache, model_cache: Option<ModelCache>) -> Self {
+        let mut model_manager = ModelManager::new();
+        if let Some(model_cache) = model_cache {
+            model_manager.set_model_cache(model_cache);
+        }
+
+        Self {
+            model_manager: Arc::new(Mutex::new(model_manager)),
+            image_processor: Arc::new(Mutex::new(ImageProcessor::new())),
+            result_cache: Arc::new(Mutex::new(result_cache)),
+            model_watch: Mutex::new(None),
+        }
+    }
+}
+
+// Result cache with its disk tier in the app cache dir, memory only if that is unavailable
+pub fn open_result_cache<R: Runtime>(app_handle: &AppHandle<R>) -> ResultCache {
+    let mut cache = ResultCache::new(RESULT_CACHE_CAPACITY);
+
+    let enabled = app_handle
+        .path()
+        .app_cache_dir()
+        .map_err(anyhow::Error::from)
+        .and_then(|dir| cache.enable_disk_tier(&dir.join("results")));
+    if let Err(e) = enabled {
+        warn!("Result cache kept in memory only: {:#}", e);
+    }
+
+    cache
+}
+
+// Prepared model cache in the app cache dir; models load from scratch without it
+pub fn open_model_cache<R: Runtime>(app_handle: &AppHandle<R>) -> Option<ModelCache> {
+    let opened = app_handle
+        .path()
+        .app_cache_dir()
+        .map_err(anyhow::Error::from)
+        .and_then(|dir| ModelCache::open(&dir.join("models")));
+
+    match opened {
+        Ok(cache) => Some(cache),
+        Err(e) => {
+            warn!("Prepared model cache disabled: {:#}", e);
+            None
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn recognize_image<R: Runtime>(
+    app_handle: AppHandle<R>,
+    image_path: String,
+    model_id: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<RecognitionResult>, String> {
+    let image_path =
+        path_scope::resolve_scoped_path(&app_handle, &image_path).map_err(|e| e.to_string())?;
+
+    recognize_path(&app_handle, &state, &image_path, model_id.as_deref()).await
+}
+
+// Recognize a file that has already been checked against the fs scope
+async fn recognize_path<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    state: &AppState,
+    image_path: &Path,
+    model_id: Option<&str>,
+) -> Result<Vec<RecognitionResult>, String> {
+    let source = image_path.to_string_lossy().into_owned();
+
+    let image_bytes = state
+        .image_processor
+        .lock()
+        .await
+        .read_file(&source)
+        .map_err(|e| e.to_string())?;
+
+    recognize_and_record(
+        app_handle,
+        state,
+        model_id,
+        source,
+        SourceKind::Path,
+        &image_bytes,
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn recognize_image_data<R: Runtime>(
+    app_handle: AppHandle<R>,
+    image_data: String,
+    model_id: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<RecognitionResult>, String> {
+    let image_bytes = state
+        .image_processor
+        .lock()
+        .await
+        .decode_base64(&image_data)
+        .map_err(|e| e.to_string())?;
+
+    recognize_and_record(
+        &app_handle,
+        &state,
+        model_id.as_deref(),
+        String::new(),
+        SourceKind::Data,
+        &image_bytes,
+    )
+    .await
+}
+
+// Recognize encoded image bytes sent as a raw IPC body (ArrayBuffer / Uint8Array)
+// A JSON array of bytes is accepted as well, e.g. from platforms without raw IPC.
+// A raw body leaves no room for arguments, so the model id comes as a header.
+#[tauri::command]
+pub async fn recognize_image_bytes<R: Runtime>(
+    app_handle: AppHandle<R>,
+    request: tauri::ipc::Request<'_>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<RecognitionResult>, String> {
+    let json_bytes: Vec<u8>;
+    let image_bytes: &[u8] = match request.body() {
+        InvokeBody::Raw(bytes) => bytes,
+        InvokeBody::Json(value) => {
+            json_bytes = serde_json::from_value(value.clone())
+                .map_err(|e| format!("Expected raw image bytes: {}", e))?;
+            &json_bytes
+        }
+    };
+    let model_id = match request.headers().get(MODEL_ID_HEADER) {
+        Some(value) => Some(
+            value
+                .to_str()
+                .map_err(|e| format!("Invalid {} header: {}", MODEL_ID_HEADER, e))?,
+        ),
+        None => None,
+    };
+
+    recognize_and_record(
+        &app_handle,
+        &state,
+        model_id,
+        String::new(),
+        SourceKind::Data,
+        image_bytes,
+    )
+    .await
+}
+
+// A registered model id, the default model's if none is given
+fn resolve_model_id(
+    model_manager: &ModelManager,
+    model_id: Option<&str>,
+) -> Result<String, String> {
+    let model_id = match model_id {
+        Some(model_id) => model_id,
+        None => model_manager
+            .default_model_id()
+            .ok_or_else(|| ModelError::NotInitialized.to_string())?,
+    };
+    if !model_manager.has_model(model_id) {
+        return Err(ModelError::UnknownModel(model_id.to_string()).to_string());
+    }
+    Ok(model_id.to_string())
+}
+
+// Recognize encoded image bytes with `model_id`, by default the default
+// model, answering from the result cache when possible, and add the outcome
+// to the history
+async fn recognize_and_record<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    state: &AppState,
+    model_id: Option<&str>,
+    source: String,
+    source_kind: SourceKind,
+    image_bytes: &[u8],
+) -> Result<Vec<RecognitionResult>, String> {
+    let model = {
+        let model_manager = state.model_manager.lock().await;
+        let model_id = resolve_model_id(&model_manager, model_id)?;
+        model_manager
+            .model_info(&model_id)
+            .cloned()
+            .ok_or_else(|| ModelError::UnknownModel(model_id).to_string())?
+    };
+
+    let profile = state.image_processor.lock().await.profile();
+    let cache_key = CacheKey::new(&sha256_hex(image_bytes), &model, &profile, DEFAULT_TOP_K);
+
+    let cached = state.result_cache.lock().await.get(&cache_key);
+    if let Some(results) = cached {
+        let image_processor = state.image_processor.lock().await.clone();
+        let thumbnail = ThumbnailSource::Encoded(image_bytes.to_vec(), image_processor);
+        let record = HistoryRecord {
+            source,
+            source_kind,
+            model_id: model.id,
+            results: results.clone(),
+            decode_time: Duration::ZERO,
+            inference_time: Duration::ZERO,
+            cached: true,
+        };
+        record_history(app_handle, record, thumbnail);
+        return Ok(results);
+    }
+
+    let decode_start = Instant::now();
+    let (img, image_data) = {
+        let image_processor = state.image_processor.lock().await;
+        let img = image_processor
+            .decode_bytes(image_bytes)
+            .map_err(|e| e.to_string())?;
+        let image_data = image_processor
+            .preprocess_image(&img)
+            .map_err(|e| e.to_string())?;
+        (img, image_data)
+    };
+    let decode_time = decode_start.elapsed();
+
+    // Inference runs on a handle, so the manager stays free for a model reload
+    let handle = state
+        .model_manager
+        .lock()
+        .await
+        .handle(&model.id)
+        .map_err(|e| e.to_string())?;
+    let inference_start = Instant::now();
+    let results: Vec<RecognitionResult> = handle
+        .recognize(&image_data, DEFAULT_TOP_K)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|(label, confidence)| RecognitionResult { label, confidence })
+        .collect();
+    let inference_time = inference_start.elapsed();
+
+    state.result_cache.lock().await.insert(&cache_key, &results);
+
+    let record = HistoryRecord {
+        source,
+        source_kind,
+        model_id: model.id,
+        results: results.clone(),
+        decode_time,
+        inference_time,
+        cached: false,
+    };
+    record_history(app_handle, record, ThumbnailSource::Decoded(img));
+
+    Ok(results)
+}
+
+struct HistoryRecord {
+    source: String,
+    source_kind: SourceKind,
+    model_id: String,
+    results: Vec<RecognitionResult>,
+    decode_time: Duration,
+    inference_time: Duration,
+    cached: bool,
+}
+
+// Cache hits skip decoding, their thumbnail is decoded while recording
+enum ThumbnailSource {
+    Decoded(DynamicImage),
+    Encoded(Vec<u8>, ImageProcessor),
+}
+
+// History is best effort: failing to record never fails the recognition
+fn record_history<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    record: HistoryRecord,
+    thumbnail: ThumbnailSource,
+) {
+    let app_handle = app_handle.clone();
+    tokio::task::spawn_blocking(move || {
+        let Some(history) = app_handle.try_state::<HistoryStore>() else {
+            return;
+        };
+
+        let thumbnail = match thumbnail {
+            ThumbnailSource::Decoded(img) => thumbnail_jpeg(&img, THUMBNAIL_SIZE),
+            ThumbnailSource::Encoded(image_bytes, image_processor) => image_processor
+                .decode_bytes(&image_bytes)
+                .and_then(|img| thumbnail_jpeg(&img, THUMBNAIL_SIZE)),
+        };
+        let thumbnail = thumbnail
+            .map_err(|e| warn!("Skipping history thumbnail: {:#}", e))
+            .ok();
+
+        let entry = NewHistoryEntry {
+            source: &record.source,
+            source_kind: record.source_kind,
+            thumbnail: thumbnail.as_deref(),
+            model_id: &record.model_id,
+            results: &record.results,
+            decode_ms: record.decode_time.as_secs_f64() * 1000.0,
+            inference_ms: record.inference_time.as_secs_f64() * 1000.0,
+            cached: record.cached,
+        };
+        if let Err(e) = history.record(&entry) {
+            warn!("Failed to record recognition history: {:#}", e);
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_cache_stats(state: tauri::State<'_, AppState>) -> Result<CacheStats, String> {
+    Ok(state.result_cache.lock().await.stats())
+}
+
+// Write the top labels of each image as XMP keywords. Defaults to a dry run,
+// and existing files are only rewritten with `confirm_overwrite`.
+#[tauri::command]
+pub async fn tag_images<R: Runtime>(
+    app_handle: AppHandle<R>,
+    paths: Vec<String>,
+    options: Option<TagOptions>,
+    model_id: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<TagReport>, String> {
+    let options = options.unwrap_or_default();
+    let images = path_scope::resolve_scoped_images(&app_handle, &paths, options.recursive)
+        .map_err(|e| e.to_string())?;
+    // Resolved once, so changing the default model midway does not mix models
+    let model_id = resolve_model_id(&*state.model_manager.lock().await, model_id.as_deref())?;
+
+    let mut reports = Vec::with_capacity(images.len());
+    for image in images {
+        let report = match recognize_path(&app_handle, &state, &image, Some(&model_id)).await {
+            Ok(results) => auto_tag::tag_image(&image, &results, &options),
+            Err(e) => TagReport::failed(&image, e),
+        };
+        reports.push(report);
+    }
+
+    Ok(reports)
+}
+
+// Plan sorting the images in `directory` into folders named after their top
+// label or category. Nothing is moved until the plan is passed to `apply_organize`.
+#[tauri::command]
+pub async fn organize_directory<R: Runtime>(
+    app_handle: AppHandle<R>,
+    directory: String,
+    options: Option<OrganizeOptions>,
+    model_id: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<OrganizeResult, String> {
+    let options = options.unwrap_or_default();
+    let directory_path =
+        path_scope::resolve_scoped_path(&app_handle, &directory).map_err(|e| e.to_string())?;
+    if !directory_path.is_dir() {
+        return Err(format!("Not a directory: {}", directory));
+    }
+
+    let destination = match &options.destination {
+        Some(destination) => {
+            path_scope::resolve_scoped_path(&app_handle, destination).map_err(|e| e.to_string())?
+        }
+        None => directory_path.clone(),
+    };
+    let categories = match &options.category_map {
+        Some(map_path) => {
+            let map_path = path_scope::resolve_scoped_path(&app_handle, map_path)
+                .map_err(|e| e.to_string())?;
+            Some(CategoryMap::load(&map_path).map_err(|e| format!("{:#}", e))?)
+        }
+        None => None,
+    };
+
+    let images = path_scope::resolve_scoped_images(&app_handle, &[directory], options.recursive)
+        .map_err(|e| e.to_string())?;
+    let model_id = resolve_model_id(&*state.model_manager.lock().await, model_id.as_deref())?;
+
+    let mut items = Vec::with_capacity(images.len());
+    let mut skipped = Vec::new();
+    for image in images {
+        match recognize_path(&app_handle, &state, &image, Some(&model_id)).await {
+            Ok(results) => items.push(OrganizeItem {
+                path: image,
+                top: results
+                    .into_iter()
+                    .next()
+                    .map(|result| (result.label, result.confidence)),
+            }),
+            Err(error) => skipped.push(FileFailure { path: image, error }),
+        }
+    }
+
+    let plan = organize::plan_organize(
+        &items,
+        &destination,
+        options.mode,
+        categories.as_ref(),
+        options.min_confidence,
+    );
+
+    let plan_id = organizer::store_plan(&app_handle, &plan).map_err(|e| format!("{:#}", e))?;
+
+    Ok(OrganizeResult {
+        plan_id,
+        plan,
+        skipped,
+    })
+}
+
+// Carry out a plan previewed by `organize_directory`, exactly as previewed.
+// Each plan can be applied once.
+#[tauri::command]
+pub async fn apply_organize<R: Runtime>(
+    app_handle: AppHandle<R>,
+    plan_id: String,
+) -> Result<AppliedOrganize, String> {
+    let applied = {
+        let plan_id = plan_id.clone();
+        tokio::task::spawn_blocking(move || organizer::apply_stored_plan(&app_handle, &plan_id))
+    }
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("{:#}", e))?;
+
+    Ok(AppliedOrganize {
+        journal_id: plan_id,
+        applied,
+    })
+}
+
+// Revert an `organize_directory` run. The journal is kept while anything
+// could not be restored, so the undo can be retried.
+#[tauri::command]
+pub async fn undo_organize<R: Runtime>(
+    app_handle: AppHandle<R>,
+    journal_id: String,
+) -> Result<UndoReport, String> {
+    let journal_path =
+        organizer::journal_path(&app_handle, &journal_id).map_err(|e| e.to_string())?;
+
+    tokio::task::spawn_blocking(move || {
+        let report = organize::undo(&journal_path)?;
+        if report.failures.is_empty() {
+            if let Err(e) = std::fs::remove_file(&journal_path) {
+                warn!(
+                    "Failed to remove organize journal {:?}: {}",
+                    journal_path, e
+                );
+            }
+        }
+        Ok::<_, anyhow::Error>(report)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn run_benchmark<R: Runtime>(
+    app_handle: AppHandle<R>,
+    mut config: BenchmarkConfig,
+    save_report: Option<bool>,
+) -> Result<BenchmarkReport, String> {
+    // Benchmark inputs are read like any other file, so they obey the same scope
+    if let Some(model_path) = config.model_path.as_mut() {
+        *model_path = path_scope::resolve_scoped_path(&app_handle, &model_path.to_string_lossy())
+            .map_err(|e| e.to_string())?;
+    }
+    for image_path in config.image_paths.iter_mut() {
+        *image_path = path_scope::resolve_scoped_path(&app_handle, &image_path.to_string_lossy())
+            .map_err(|e| e.to_string())?;
+    }
+
+    // The benchmark loads its own copies of the model, so it never blocks AppState
+    let report = tokio::task::spawn_blocking(move || benchmark::run_benchmark(&config))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    // Ask for the destination after the run, so a cancelled dialog loses nothing
+    if save_report.unwrap_or(false) {
+        if let Some(path) = export::pick_save_file(&app_handle, "JSON", "json", "benchmark.json")
+            .await
+            .map_err(|e| e.to_string())?
+        {
+            report.write_json(&path).map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(report)
+}
+
+// Read a content URI off the async runtime, JNI calls block the calling thread
+async fn read_content_uri_bytes(uri: String) -> Result<Vec<u8>, String> {
+    tokio::task::spawn_blocking(move || content_uri::read_content_uri(&uri))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn read_content_uri(uri: String) -> Result<String, String> {
+    debug!("Reading content URI: {}", uri);
+
+    let bytes = read_content_uri_bytes(uri).await?;
+
+    // Return base64 encoded data
+    Ok(general_purpose::STANDARD.encode(&bytes))
+}
+
+#[tauri::command]
+pub async fn get_content_uri_base64(uri: String) -> Result<String, String> {
+    read_content_uri(uri).await
+}
+
+#[tauri::command]
+pub async fn recognize_content_uri<R: Runtime>(
+    app_handle: AppHandle<R>,
+    uri: String,
+    model_id: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<RecognitionResult>, String> {
+    debug!("Recognizing content URI: {}", uri);
+
+    let bytes = read_content_uri_bytes(uri.clone()).await?;
+
+    recognize_and_record(
+        &app_handle,
+        &state,
+        model_id.as_deref(),
+        uri,
+        SourceKind::ContentUri,
+        &bytes,
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn list_history(
+    offset: Option<u32>,
+    limit: Option<u32>,
+    history: tauri::State<'_, HistoryStore>,
+) -> Result<HistoryPage, String> {
+    history
+        .list(
+            offset.unwrap_or(0),
+            limit.unwrap_or(DEFAULT_HISTORY_PAGE_SIZE),
+        )
+        .map_err(|e| e.to_string())
+}
+
+// Entries with a result label containing `label`, case-insensitively
+#[tauri::command]
+pub async fn search_history(
+    label: String,
+    offset: Option<u32>,
+    limit: Option<u32>,
+    history: tauri::State<'_, HistoryStore>,
+) -> Result<HistoryPage, String> {
+    history
+        .search(
+            &label,
+            offset.unwrap_or(0),
+            limit.unwrap_or(DEFAULT_HISTORY_PAGE_SIZE),
+        )
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_history_entry(
+    id: i64,
+    history: tauri::State<'_, HistoryStore>,
+) -> Result<bool, String> {
+    history.delete(id).map_err(|e| e.to_string())
+}
+
+// Export stored results to a file picked in a save dialog.
+// Returns the written path, or None if the user cancelled.
+#[tauri::command]
+pub async fn export_history<R: Runtime>(
+    app_handle: AppHandle<R>,
+    format: ExportFormat,
+    label: Option<String>,
+    state: tauri::State<'_, AppState>,
+    history: tauri::State<'_, HistoryStore>,
+) -> Result<Option<String>, String> {
+    let Some(path) = export::pick_save_path(&app_handle, format)
+        .await
+        .map_err(|e| e.to_string())?
+    else {
+        return Ok(None);
+    };
+
+    let entries = history
+        .entries(label.as_deref())
+        .map_err(|e| e.to_string())?;
+
+    // Labels files of the models still registered, for stable COCO category ids
+    let model_labels: ModelLabels = {
+        let model_manager = state.model_manager.lock().await;
+        let model_ids: HashSet<&str> = entries.iter().map(|e| e.model_id.as_str()).collect();
+        model_ids
+            .into_iter()
+            .filter_map(|model_id| {
+                let labels = model_manager.labels(model_id)?;
+                Some((model_id.to_string(), labels.to_vec()))
+            })
+            .collect()
+    };
+
+    export::write_export_file(&path, format, &entries, &model_labels).map_err(|e| e.to_string())?;
+
+    Ok(Some(path.to_string_lossy().into_owned()))
+}
+
+#[tauri::command]
+pub async fn clear_history(history: tauri::State<'_, HistoryStore>) -> Result<usize, String> {
+    history.clear().map_err(|e| e.to_string())
+}
+
+// Initialize from the model files embedded in the binary
+#[cfg(any(target_os = "android", feature = "mobile"))]
+fn init_android(model_manager: &mut ModelManager) -> anyhow::Result<()> {
+    use taurivision_core::model_integrity;
+
+    // Embedded model files - paths are relative to src-tauri/src/
+    const MODEL_BYTES: &[u8] = include_bytes!("../assets/model/mobilenet_v2.onnx");
+    const MODEL_SHA256: &str = include_str!("../assets/model/mobilenet_v2.onnx.sha256");
+    const LABELS_BYTES: &[u8] = include_bytes!("../assets/model/labels.txt");
+
+    let expected = model_integrity::parse_checksum("embedded model checksum", MODEL_SHA256)?;
+    model_integrity::verify_checksum("embedded model", MODEL_BYTES, &expected)?;
+    model_manager.init_from_bytes("mobilenet_v2", MODEL_BYTES, LABELS_BYTES)
+}
+
+#[tauri::command]
+pub async fn init_model<R: Runtime>(
+    app_handle: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    println!("DEBUG POINT: init_model function called");
+
+    let mut model_manager = state.model_manager.lock().await;
+
+    // Print more debug info about the environment
+    let platform_type = if cfg!(target_os = "android") {
+        "Android (compile-time check)"
+    } else if cfg!(any(target_os = "ios", target_os = "android")) {
+        "Mobile device (compile-time check)"
+    } else {
+        "Desktop (compile-time check)"
+    };
+
+    println!("Platform detected as: {}", platform_type);
+
+    // Check for "android" in the current binary path as a runtime check
+    let is_runtime_android = std::env::current_exe()
+        .map(|path| path.to_string_lossy().contains("android"))
+        .unwrap_or(false);
+
+    println!("Runtime Android check: {}", is_runtime_android);
+
+    // For Android, try the byte-embedded approach first
+    #[cfg(any(target_os = "android", feature = "mobile"))]
+    {
+        println!("Attempting Android/mobile initialization");
+        match init_android(&mut model_manager) {
+            Ok(_) => println!("Android direct initialization successful"),
+            Err(e) => {
+                println!("Android direct initialization failed: {}", e);
+                println!("Falling back to standard initialization");
+            }
+        }
+    }
+
+    // Bundled and imported models, resolved independently of the working directory.
+    // An embedded model registered above stays the default.
+    let registered = model_store::register_discovered(&app_handle, &mut model_manager);
+    if model_manager.is_initialized() {
+        info!("Registered {} discovered model(s)", registered);
+        return Ok("Model initialized successfully".to_string());
+    }
+
+    // If we're still here, try standard initialization for any platform
+    println!("Attempting standard file-based initialization");
+    model_manager.init().map_err(|e| {
+        println!("Standard initialization failed: {}", e);
+        e.to_string()
+    })?;
+
+    Ok("Model initialized successfully".to_string())
+}
+
+#[tauri::command]
+pub async fn list_models(state: tauri::State<'_, AppState>) -> Result<Vec<ModelInfo>, String> {
+    Ok(state.model_manager.lock().await.models())
+}
+
+// Let the user pick a model file and its labels, or a model package, copy them
+// into the app data dir and register the model. Returns None if a dialog was cancelled.
+#[tauri::command]
+pub async fn import_model<R: Runtime>(
+    app_handle: AppHandle<R>,
+    model_id: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<ModelInfo>, String> {
+    let Some(picked) = model_store::pick_model_files(&app_handle)
+        .await
+        .map_err(|e| format!("{:#}", e))?
+    else {
+        return Ok(None);
+    };
+    let model_id = model_id.unwrap_or_else(|| picked.name.clone());
+
+    if state.model_manager.lock().await.has_model(&model_id) {
+        return Err(format!("Model id {} is already in use", model_id));
+    }
+
+    // Checked before installing, so a broken keys file does not leave files behind
+    let trusted_keys = model_store::trusted_keys(&app_handle).map_err(|e| format!("{:#}", e))?;
+    let source =
+        model_store::install(&app_handle, &model_id, &picked).map_err(|e| format!("{:#}", e))?;
+
+    let job = state.model_manager.lock().await.register_job(
+        &source.id,
+        &source.model_path,
+        &source.labels_path,
+        trusted_keys.as_ref(),
+    );
+    // Built without holding the lock, so recognition keeps running meanwhile
+    let registered = match tokio::task::spawn_blocking(move || job.run()).await {
+        Ok(Ok(prepared)) => state.model_manager.lock().await.finish_register(prepared),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(e.into()),
+    };
+    match registered {
+        Ok(info) => Ok(Some(info)),
+        Err(e) => {
+            // Keep a model that cannot load from being rediscovered on every start
+            model_store::uninstall(&app_handle, &model_id);
+            Err(format!("{:#}", e))
+        }
+    }
+}
+
+// Make a registered model, e.g. an imported one or an ensemble, the one
+// recognition, tagging and organizing use
+#[tauri::command]
+pub async fn set_default_model(
+    model_id: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<ModelInfo, String> {
+    let mut model_manager = state.model_manager.lock().await;
+    model_manager
+        .set_default_model(&model_id)
+        .map_err(|e| e.to_string())?;
+    model_manager
+        .model_info(&model_id)
+        .cloned()
+        .ok_or_else(|| ModelError::UnknownModel(model_id).to_string())
+}
+
+// Reload a model whenever its files change on disk, by default the active
+// model. For an ensemble, its members are watched and the ensemble follows
+// them. One model is watched at a time; returns the id of the watched model.
+#[tauri::command]
+pub async fn watch_model<R: Runtime>(
+    app_handle: AppHandle<R>,
+    model_id: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    let (model_id, sources) = {
+        let model_manager = state.model_manager.lock().await;
+        let model_id = resolve_model_id(&model_manager, model_id.as_deref())?;
+        // Members loaded from memory have nothing to watch
+        let sources: Vec<(String, Vec<PathBuf>)> = model_manager
+            .member_ids(&model_id)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|member_id| {
+                let paths = model_manager.source_paths(&member_id)?;
+                Some((member_id, paths))
+            })
+            .collect();
+        if sources.is_empty() {
+            return Err(format!(
+                "Model {} was not loaded from a file and cannot be watched",
+                model_id
+            ));
+        }
+        (model_id, sources)
+    };
+
+    let watch = model_watch::start(&app_handle, Arc::clone(&state.model_manager), &sources)
+        .map_err(|e| format!("{:#}", e))?;
+    // Replacing the previous watch stops it
+    *state.model_watch.lock().await = Some(watch);
+
+    Ok(model_id)
+}
+
+// Stop hot reloading; returns whether a model was being watched
+#[tauri::command]
+pub async fn unwatch_model(state: tauri::State<'_, AppState>) -> Result<bool, String> {
+    Ok(state.model_watch.lock().await.take().is_some())
+}
+
+// Describe a model file, or the file a registered model was loaded from (by
+// default the active model), without running inference on it. An ensemble
+// gets a report per member, in member order.
+#[tauri::command]
+pub async fn inspect_model<R: Runtime>(
+    app_handle: AppHandle<R>,
+    path: Option<String>,
+    model_id: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<InspectionReport>, String> {
+    let model_paths = match path {
+        Some(path) => {
+            vec![path_scope::resolve_scoped_path(&app_handle, &path).map_err(|e| e.to_string())?]
+        }
+        None => {
+            let model_manager = state.model_manager.lock().await;
+            let model_id = resolve_model_id(&model_manager, model_id.as_deref())?;
+            model_manager
+                .member_ids(&model_id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|member_id| {
+                    model_manager
+                        .source_paths(&member_id)
+                        .and_then(|paths| paths.into_iter().next())
+                        .ok_or_else(|| format!("Model {} was not loaded from a file", member_id))
+                })
+                .collect::<Result<Vec<_>, String>>()?
+        }
+    };
+
+    tokio::task::spawn_blocking(move || {
+        model_paths
+            .iter()
+            .map(|model_path| {
+                let bytes = std::fs::read(model_path)
+                    .with_context(|| format!("Failed to read {:?}", model_path))?;
+                model_inspect::inspect(Some(model_path.as_path()), &bytes)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("{:#}", e))
+}
+
+// Run two registered models on the same images or folders and report where
+// they disagree. `save_report` additionally saves the report as JSON where the
+// user picks in the save dialog.
+#[tauri::command]
+pub async fn compare_models<R: Runtime>(
+    app_handle: AppHandle<R>,
+    model_a: String,
+    model_b: String,
+    paths: Vec<String>,
+    options: Option<CompareOptions>,
+    save_report: Option<bool>,
+    state: tauri::State<'_, AppState>,
+) -> Result<AbReport, String> {
+    let options = options.unwrap_or_default();
+    let images = path_scope::resolve_scoped_images(&app_handle, &paths, options.recursive)
+        .map_err(|e| e.to_string())?;
+
+    // Handles keep both models usable while the manager serves other requests
+    let (model_a, model_b) = {
+        let model_manager = state.model_manager.lock().await;
+        let model_a = model_manager.handle(&model_a).map_err(|e| e.to_string())?;
+        let model_b = model_manager.handle(&model_b).map_err(|e| e.to_string())?;
+        (model_a, model_b)
+    };
+    let image_processor = state.image_processor.lock().await.clone();
+
+    let report = tokio::task::spawn_blocking(move || {
+        model_compare::compare(&model_a, &model_b, &images, &image_processor, &options)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("{:#}", e))?;
+
+    if save_report.unwrap_or(false) {
+        if let Some(path) = export::pick_save_file(&app_handle, "JSON", "json", "comparison.json")
+            .await
+            .map_err(|e| e.to_string())?
+        {
+            report.write_json(&path).map_err(|e| format!("{:#}", e))?;
+        }
+    }
+
+    Ok(report)
+}
+
+// Combine registered models into a virtual model registered under `config.id`,
+// usable wherever a model id is. The ensemble is saved and restored on the next start.
+#[tauri::command]
+pub async fn create_ensemble<R: Runtime>(
+    app_handle: AppHandle<R>,
+    mut config: EnsembleConfig,
+    state: tauri::State<'_, AppState>,
+) -> Result<ModelInfo, String> {
+    // Label maps are read like any other user file, so they obey the same scope
+    for member in config.members.iter_mut() {
+        if let Some(label_map) = member.label_map.as_mut() {
+            *label_map = path_scope::resolve_scoped_path(&app_handle, &label_map.to_string_lossy())
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    let mut model_manager = state.model_manager.lock().await;
+    let info = model_manager
+        .register_ensemble(&config)
+        .map_err(|e| format!("{:#}", e))?;
+    if let Err(e) = model_store::save_ensemble(&app_handle, &config) {
+        // An ensemble that would be gone after a restart is not worth keeping
+        model_manager.remove_ensemble(&config.id);
+        return Err(format!("{:#}", e));
+    }
+
+    Ok(info)
+}
+
+// Unregister and forget an ensemble; returns whether there was one
+#[tauri::command]
+pub async fn remove_ensemble<R: Runtime>(
+    app_handle: AppHandle<R>,
+    model_id: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<bool, String> {
+    let removed = state.model_manager.lock().await.remove_ensemble(&model_id);
+    let forgotten =
+        model_store::forget_ensemble(&app_handle, &model_id).map_err(|e| format!("{:#}", e))?;
+    Ok(removed || forgotten)
+}
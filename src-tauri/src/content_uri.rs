@@ -0,0 +1,185 @@
+use anyhow::Result;
+#[cfg(any(target_os = "android", test))]
+use std::io::Read;
+
+// Providers can serve arbitrarily large or endless streams, so reads stop here
+#[cfg(target_os = "android")]
+const MAX_IMAGE_BYTES: u64 = taurivision_core::image_processor::DEFAULT_MAX_IMAGE_BYTES;
+
+// Read the full contents of an Android content:// URI
+#[cfg(target_os = "android")]
+pub fn read_content_uri(uri: &str) -> Result<Vec<u8>> {
+    android::read_to_end(uri, MAX_IMAGE_BYTES)
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn read_content_uri(_uri: &str) -> Result<Vec<u8>> {
+    Err(anyhow::anyhow!(
+        "Content URI handling is only supported on Android"
+    ))
+}
+
+// Read a stream to the end, failing instead of truncating once it exceeds `max_bytes`
+#[cfg(any(target_os = "android", test))]
+fn read_limited<R: Read>(reader: R, max_bytes: u64) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.take(max_bytes + 1).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > max_bytes {
+        anyhow::bail!("Content URI exceeds the limit of {} bytes", max_bytes);
+    }
+    Ok(bytes)
+}
+
+#[cfg(target_os = "android")]
+mod android {
+    use anyhow::{Context, Result};
+    use jni::objects::{JByteArray, JObject, JValue};
+    use jni::{JNIEnv, JavaVM};
+    use std::io::{self, Read};
+
+    // Size of the Java byte[] reused for every InputStream.read call
+    const BUFFER_SIZE: usize = 64 * 1024;
+
+    pub fn read_to_end(uri: &str, max_bytes: u64) -> Result<Vec<u8>> {
+        let ctx = ndk_context::android_context();
+        let vm = unsafe { JavaVM::from_raw(ctx.vm().cast()) }.context("Failed to get JavaVM")?;
+        let mut env = vm
+            .attach_current_thread()
+            .context("Failed to attach thread")?;
+
+        // The Android context is a global reference owned by ndk-context
+        let context = unsafe { JObject::from_raw(ctx.context().cast()) };
+
+        // Local references created while reading are released with the frame
+        env.with_local_frame(16, |env| -> Result<Vec<u8>> {
+            let reader = ContentUriReader::open(env, &context, uri)?;
+            super::read_limited(reader, max_bytes).context("Failed to read content URI")
+        })
+    }
+
+    // `std::io::Read` over a java.io.InputStream opened through the ContentResolver
+    pub struct ContentUriReader<'a, 'local> {
+        env: &'a mut JNIEnv<'local>,
+        stream: JObject<'local>,
+        buffer: JByteArray<'local>,
+    }
+
+    impl<'a, 'local> ContentUriReader<'a, 'local> {
+        pub fn open(env: &'a mut JNIEnv<'local>, context: &JObject, uri: &str) -> Result<Self> {
+            let resolver = env.call_method(
+                context,
+                "getContentResolver",
+                "()Landroid/content/ContentResolver;",
+                &[],
+            );
+            let resolver = check(env, resolver.and_then(|v| v.l()), "call getContentResolver")?;
+
+            let uri_string = env.new_string(uri);
+            let uri_string = check(env, uri_string, "create Java string")?;
+
+            let uri_obj = env.call_static_method(
+                "android/net/Uri",
+                "parse",
+                "(Ljava/lang/String;)Landroid/net/Uri;",
+                &[(&uri_string).into()],
+            );
+            let uri_obj = check(env, uri_obj.and_then(|v| v.l()), "call Uri.parse")?;
+
+            let stream = env.call_method(
+                &resolver,
+                "openInputStream",
+                "(Landroid/net/Uri;)Ljava/io/InputStream;",
+                &[(&uri_obj).into()],
+            );
+            let stream = check(env, stream.and_then(|v| v.l()), "call openInputStream")?;
+
+            if stream.is_null() {
+                anyhow::bail!("Failed to open input stream: null returned");
+            }
+
+            let buffer = env.new_byte_array(BUFFER_SIZE as i32);
+            let buffer = check(env, buffer, "allocate byte array")?;
+
+            Ok(Self {
+                env,
+                stream,
+                buffer,
+            })
+        }
+    }
+
+    impl Read for ContentUriReader<'_, '_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(BUFFER_SIZE);
+            if len == 0 {
+                return Ok(0);
+            }
+
+            let count = self.env.call_method(
+                &self.stream,
+                "read",
+                "([BII)I",
+                &[
+                    (&self.buffer).into(),
+                    JValue::Int(0),
+                    JValue::Int(len as i32),
+                ],
+            );
+            let count = check(self.env, count.and_then(|v| v.i()), "call InputStream.read")
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+            // InputStream.read returns -1 at end of stream
+            if count <= 0 {
+                return Ok(0);
+            }
+            let count = count as usize;
+
+            // Java bytes are signed; copy straight into the caller's buffer
+            let dst =
+                unsafe { std::slice::from_raw_parts_mut(buf.as_mut_ptr().cast::<i8>(), count) };
+            let copied = self.env.get_byte_array_region(&self.buffer, 0, dst);
+            check(self.env, copied, "copy byte array region")
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+            Ok(count)
+        }
+    }
+
+    impl Drop for ContentUriReader<'_, '_> {
+        fn drop(&mut self) {
+            let closed = self.env.call_method(&self.stream, "close", "()V", &[]);
+            if closed.is_err() {
+                let _ = self.env.exception_clear();
+            }
+        }
+    }
+
+    // Clear any pending Java exception so later JNI calls stay valid
+    fn check<T>(env: &mut JNIEnv, result: jni::errors::Result<T>, what: &str) -> Result<T> {
+        result.map_err(|e| {
+            if env.exception_check().unwrap_or(false) {
+                let _ = env.exception_clear();
+            }
+            anyhow::anyhow!("Failed to {}: {}", what, e)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{self, Cursor};
+
+    #[test]
+    fn reads_streams_up_to_the_limit() {
+        let bytes = read_limited(Cursor::new(vec![7u8; 16]), 16).unwrap();
+        assert_eq!(bytes, vec![7u8; 16]);
+    }
+
+    #[test]
+    fn rejects_streams_over_the_limit() {
+        assert!(read_limited(Cursor::new(vec![7u8; 17]), 16).is_err());
+        // An endless stream stops one byte past the limit
+        assert!(read_limited(io::repeat(7), 1024).is_err());
+    }
+}
@@ -0,0 +1,428 @@
+use crate::history::{HistoryEntry, SourceKind};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_dialog::DialogExt;
+use taurivision_core::csv::csv_field;
+use taurivision_core::RecognitionResult;
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    // One row per result
+    Csv,
+    // One JSON object per recognition
+    Jsonl,
+    // COCO-style images, annotations and categories
+    Coco,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Coco => "json",
+        }
+    }
+
+    fn filter_name(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Jsonl => "JSON Lines",
+            ExportFormat::Coco => "COCO JSON",
+        }
+    }
+}
+
+// Ask the user where to save an export; None if the dialog was cancelled
+pub async fn pick_save_path<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    format: ExportFormat,
+) -> Result<Option<PathBuf>> {
+    pick_save_file(
+        app_handle,
+        format.filter_name(),
+        format.extension(),
+        &format!("recognitions.{}", format.extension()),
+    )
+    .await
+}
+
+// Ask the user where to save a file. Paths picked in the dialog are granted by
+// the user, so they need not be inside the app's path scope.
+pub async fn pick_save_file<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    filter_name: &str,
+    extension: &str,
+    file_name: &str,
+) -> Result<Option<PathBuf>> {
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+
+    app_handle
+        .dialog()
+        .file()
+        .add_filter(filter_name, &[extension])
+        .set_file_name(file_name)
+        .save_file(move |path| {
+            let _ = sender.send(path);
+        });
+
+    let Some(path) = receiver.await.context("Save dialog closed unexpectedly")? else {
+        return Ok(None);
+    };
+
+    // Android returns content URIs, which cannot be written through std::fs
+    let path = path
+        .into_path()
+        .context("Export destination must be a file path")?;
+    Ok(Some(path))
+}
+
+// Labels of the exported models in output index order, keyed by model id
+pub type ModelLabels = BTreeMap<String, Vec<String>>;
+
+// Write `entries` to `path`. COCO category ids follow `model_labels`.
+pub fn write_export_file(
+    path: &Path,
+    format: ExportFormat,
+    entries: &[HistoryEntry],
+    model_labels: &ModelLabels,
+) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("Failed to create {:?}", path))?;
+    let mut writer = BufWriter::new(file);
+
+    match format {
+        ExportFormat::Csv => write_csv(&mut writer, entries)?,
+        ExportFormat::Jsonl => write_jsonl(&mut writer, entries)?,
+        ExportFormat::Coco => write_coco(&mut writer, entries, model_labels)?,
+    }
+
+    writer
+        .flush()
+        .with_context(|| format!("Failed to write {:?}", path))
+}
+
+fn write_csv<W: Write>(writer: &mut W, entries: &[HistoryEntry]) -> Result<()> {
+    writeln!(
+        writer,
+        "id,created_at,source,source_kind,model_id,rank,label,confidence"
+    )?;
+
+    for entry in entries {
+        for (rank, result) in entry.results.iter().enumerate() {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{}",
+                entry.id,
+                entry.created_at,
+                csv_field(&entry.source),
+                entry.source_kind.as_str(),
+                csv_field(&entry.model_id),
+                rank + 1,
+                csv_field(&result.label),
+                result.confidence
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+// History entry without its thumbnail
+#[derive(Serialize)]
+struct JsonlRecord<'a> {
+    id: i64,
+    created_at: i64,
+    source: &'a str,
+    source_kind: SourceKind,
+    model_id: &'a str,
+    results: &'a [RecognitionResult],
+    decode_ms: f64,
+    inference_ms: f64,
+    cached: bool,
+}
+
+fn write_jsonl<W: Write>(writer: &mut W, entries: &[HistoryEntry]) -> Result<()> {
+    for entry in entries {
+        let record = JsonlRecord {
+            id: entry.id,
+            created_at: entry.created_at,
+            source: &entry.source,
+            source_kind: entry.source_kind,
+            model_id: &entry.model_id,
+            results: &entry.results,
+            decode_ms: entry.decode_ms,
+            inference_ms: entry.inference_ms,
+            cached: entry.cached,
+        };
+        serde_json::to_writer(&mut *writer, &record)?;
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct CocoExport<'a> {
+    info: CocoInfo,
+    images: Vec<CocoImage<'a>>,
+    annotations: Vec<CocoAnnotation>,
+    categories: Vec<CocoCategory<'a>>,
+}
+
+#[derive(Serialize)]
+struct CocoInfo {
+    description: &'static str,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+struct CocoImage<'a> {
+    id: i64,
+    file_name: &'a str,
+    source_kind: SourceKind,
+    model_id: &'a str,
+    created_at: i64,
+}
+
+// Whole-image classification, so annotations carry a score but no bbox
+#[derive(Serialize)]
+struct CocoAnnotation {
+    id: usize,
+    image_id: i64,
+    category_id: usize,
+    score: f32,
+    rank: usize,
+}
+
+// One category per label and model, so models sharing a label name stay apart
+#[derive(Serialize)]
+struct CocoCategory<'a> {
+    id: usize,
+    name: &'a str,
+    supercategory: &'a str,
+}
+
+fn write_coco<'a, W: Write>(
+    writer: &mut W,
+    entries: &'a [HistoryEntry],
+    model_labels: &'a ModelLabels,
+) -> Result<()> {
+    // COCO category ids start at 1. Each exported model gets a block of ids in
+    // model id order and line N of its labels file is id block start + N - 1,
+    // so ids do not depend on which rows come first.
+    let mut categories: Vec<CocoCategory> = Vec::new();
+    let mut category_ids: HashMap<(&str, &str), usize> = HashMap::new();
+
+    let model_ids: BTreeSet<&str> = entries.iter().map(|e| e.model_id.as_str()).collect();
+    for &model_id in &model_ids {
+        for name in model_labels.get(model_id).into_iter().flatten() {
+            let id = categories.len() + 1;
+            category_ids.entry((model_id, name)).or_insert(id);
+            categories.push(CocoCategory {
+                id,
+                name,
+                supercategory: model_id,
+            });
+        }
+    }
+
+    // Labels missing from the labels files, e.g. from a removed model, follow in sorted order
+    let unknown: BTreeSet<(&str, &str)> = entries
+        .iter()
+        .flat_map(|entry| {
+            entry
+                .results
+                .iter()
+                .map(|result| (entry.model_id.as_str(), result.label.as_str()))
+        })
+        .filter(|key| !category_ids.contains_key(key))
+        .collect();
+    for (model_id, name) in unknown {
+        let id = categories.len() + 1;
+        category_ids.insert((model_id, name), id);
+        categories.push(CocoCategory {
+            id,
+            name,
+            supercategory: model_id,
+        });
+    }
+
+    let mut images = Vec::with_capacity(entries.len());
+    let mut annotations = Vec::new();
+
+    for entry in entries {
+        images.push(CocoImage {
+            id: entry.id,
+            file_name: &entry.source,
+            source_kind: entry.source_kind,
+            model_id: &entry.model_id,
+            created_at: entry.created_at,
+        });
+
+        for (rank, result) in entry.results.iter().enumerate() {
+            let category_id = category_ids[&(entry.model_id.as_str(), result.label.as_str())];
+
+            annotations.push(CocoAnnotation {
+                id: annotations.len() + 1,
+                image_id: entry.id,
+                category_id,
+                score: result.confidence,
+                rank: rank + 1,
+            });
+        }
+    }
+
+    let export = CocoExport {
+        info: CocoInfo {
+            description: "TauriVision recognition results",
+            version: env!("CARGO_PKG_VERSION"),
+        },
+        images,
+        annotations,
+        categories,
+    };
+
+    serde_json::to_writer_pretty(writer, &export)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: i64, model_id: &str, labels: &[&str]) -> HistoryEntry {
+        HistoryEntry {
+            id,
+            source: format!("{}.jpg", id),
+            source_kind: SourceKind::Path,
+            thumbnail: None,
+            created_at: id,
+            model_id: model_id.to_string(),
+            results: labels
+                .iter()
+                .map(|label| RecognitionResult {
+                    label: label.to_string(),
+                    confidence: 0.5,
+                })
+                .collect(),
+            decode_ms: 1.0,
+            inference_ms: 2.0,
+            cached: false,
+        }
+    }
+
+    fn model_labels() -> ModelLabels {
+        [
+            ("mobilenet", vec!["background", "cat", "dog"]),
+            ("pets", vec!["dog", "cat"]),
+        ]
+        .into_iter()
+        .map(|(model_id, labels)| {
+            let labels = labels.into_iter().map(str::to_string).collect();
+            (model_id.to_string(), labels)
+        })
+        .collect()
+    }
+
+    // (id, name, supercategory) per category
+    type Categories = Vec<(u64, String, String)>;
+
+    // Categories and (image id, category id) per annotation
+    fn write_coco_ids(entries: &[HistoryEntry]) -> (Categories, Vec<(i64, u64)>) {
+        let mut output = Vec::new();
+        write_coco(&mut output, entries, &model_labels()).unwrap();
+        let export: serde_json::Value = serde_json::from_slice(&output).unwrap();
+
+        let categories = export["categories"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|category| {
+                (
+                    category["id"].as_u64().unwrap(),
+                    category["name"].as_str().unwrap().to_string(),
+                    category["supercategory"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect();
+        let annotations = export["annotations"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|annotation| {
+                (
+                    annotation["image_id"].as_i64().unwrap(),
+                    annotation["category_id"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+        (categories, annotations)
+    }
+
+    #[test]
+    fn coco_category_ids_follow_labels_files() {
+        let entries = [
+            entry(1, "mobilenet", &["cat", "dog"]),
+            entry(2, "pets", &["cat"]),
+            entry(3, "removed", &["bird"]),
+            entry(4, "mobilenet", &["zebra"]),
+        ];
+
+        let (categories, annotations) = write_coco_ids(&entries);
+
+        // Line N of each labels file, then labels no labels file has
+        let expected: Categories = [
+            (1, "background", "mobilenet"),
+            (2, "cat", "mobilenet"),
+            (3, "dog", "mobilenet"),
+            (4, "dog", "pets"),
+            (5, "cat", "pets"),
+            (6, "zebra", "mobilenet"),
+            (7, "bird", "removed"),
+        ]
+        .into_iter()
+        .map(|(id, name, model_id)| (id, name.to_string(), model_id.to_string()))
+        .collect();
+        assert_eq!(categories, expected);
+        assert_eq!(annotations, vec![(1, 2), (1, 3), (2, 5), (3, 7), (4, 6)]);
+    }
+
+    #[test]
+    fn coco_category_ids_do_not_depend_on_row_order() {
+        let mut entries = vec![
+            entry(1, "pets", &["dog"]),
+            entry(2, "removed", &["bird"]),
+            entry(3, "mobilenet", &["zebra", "cat"]),
+            entry(4, "removed", &["apple"]),
+        ];
+        let (categories, mut annotations) = write_coco_ids(&entries);
+
+        entries.reverse();
+        let (reversed_categories, mut reversed_annotations) = write_coco_ids(&entries);
+
+        assert_eq!(categories, reversed_categories);
+        annotations.sort();
+        reversed_annotations.sort();
+        assert_eq!(annotations, reversed_annotations);
+    }
+
+    #[test]
+    fn csv_rows_quote_fields() {
+        let entries = [entry(1, "mobilenet", &["tabby, tabby cat"])];
+
+        let mut output = Vec::new();
+        write_csv(&mut output, &entries).unwrap();
+
+        let csv = String::from_utf8(output).unwrap();
+        assert_eq!(
+            csv.lines().nth(1).unwrap(),
+            "1,1,1.jpg,path,mobilenet,1,\"tabby, tabby cat\",0.5"
+        );
+    }
+}
@@ -0,0 +1,448 @@
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use log::{error, info};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, Runtime};
+use taurivision_core::RecognitionResult;
+
+// File name of the history database inside the app data dir
+pub const HISTORY_DB_FILE: &str = "history.sqlite3";
+
+// Largest page a single list/search call may return
+const MAX_PAGE_SIZE: u32 = 200;
+
+// Schema migrations, applied in order and tracked through `PRAGMA user_version`.
+// Released entries must never change: evolve the schema by appending a new one.
+const MIGRATIONS: &[&str] = &[
+    // 1: recognitions with their ranked results
+    r#"
+    CREATE TABLE recognitions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        source TEXT NOT NULL,
+        source_kind TEXT NOT NULL,
+        thumbnail BLOB,
+        created_at INTEGER NOT NULL,
+        model_id TEXT NOT NULL,
+        decode_ms REAL NOT NULL,
+        inference_ms REAL NOT NULL
+    );
+    CREATE INDEX recognitions_created_at ON recognitions (created_at);
+
+    CREATE TABLE recognition_results (
+        recognition_id INTEGER NOT NULL REFERENCES recognitions (id) ON DELETE CASCADE,
+        rank INTEGER NOT NULL,
+        label TEXT NOT NULL,
+        confidence REAL NOT NULL,
+        PRIMARY KEY (recognition_id, rank)
+    );
+    CREATE INDEX recognition_results_label ON recognition_results (label);
+    "#,
+    // 2: results served from the result cache
+    r#"
+    ALTER TABLE recognitions ADD COLUMN cached INTEGER NOT NULL DEFAULT 0;
+    "#,
+];
+
+// Where a recognized image came from
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    Path,
+    ContentUri,
+    Data,
+}
+
+impl SourceKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SourceKind::Path => "path",
+            SourceKind::ContentUri => "content_uri",
+            SourceKind::Data => "data",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "path" => SourceKind::Path,
+            "content_uri" => SourceKind::ContentUri,
+            _ => SourceKind::Data,
+        }
+    }
+}
+
+// A recognition about to be recorded
+pub struct NewHistoryEntry<'a> {
+    pub source: &'a str,
+    pub source_kind: SourceKind,
+    pub thumbnail: Option<&'a [u8]>,
+    pub model_id: &'a str,
+    pub results: &'a [RecognitionResult],
+    pub decode_ms: f64,
+    pub inference_ms: f64,
+    pub cached: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct HistoryEntry {
+    pub id: i64,
+    pub source: String,
+    pub source_kind: SourceKind,
+    // Base64-encoded JPEG
+    pub thumbnail: Option<String>,
+    // Milliseconds since the Unix epoch
+    pub created_at: i64,
+    pub model_id: String,
+    pub results: Vec<RecognitionResult>,
+    pub decode_ms: f64,
+    pub inference_ms: f64,
+    // Served from the result cache, timings are zero
+    pub cached: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct HistoryPage {
+    pub entries: Vec<HistoryEntry>,
+    pub total: u64,
+    pub offset: u32,
+    pub limit: u32,
+}
+
+// Open the history database in the app data dir and manage it as app state.
+// Recognition keeps working without history if the database cannot be opened.
+pub fn init<R: Runtime>(app_handle: &AppHandle<R>) {
+    let path = match app_handle.path().app_data_dir() {
+        Ok(dir) => dir.join(HISTORY_DB_FILE),
+        Err(e) => {
+            error!("Recognition history disabled, no app data dir: {}", e);
+            return;
+        }
+    };
+
+    match HistoryStore::open(&path) {
+        Ok(store) => {
+            app_handle.manage(store);
+        }
+        Err(e) => error!("Recognition history disabled: {:#}", e),
+    }
+}
+
+// Recognition history kept in an embedded SQLite database
+pub struct HistoryStore {
+    conn: Mutex<Connection>,
+}
+
+impl HistoryStore {
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut conn = Connection::open(path)
+            .with_context(|| format!("Failed to open history database: {:?}", path))?;
+
+        // Needed for ON DELETE CASCADE, off by default in SQLite
+        conn.pragma_update(None, "foreign_keys", true)?;
+        migrate(&mut conn)?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    pub fn record(&self, entry: &NewHistoryEntry) -> Result<i64> {
+        let mut conn = self.lock()?;
+        let tx = conn.transaction()?;
+
+        tx.execute(
+            "INSERT INTO recognitions
+                (source, source_kind, thumbnail, created_at, model_id, decode_ms, inference_ms, cached)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                entry.source,
+                entry.source_kind.as_str(),
+                entry.thumbnail,
+                now_millis(),
+                entry.model_id,
+                entry.decode_ms,
+                entry.inference_ms,
+                entry.cached,
+            ],
+        )?;
+        let id = tx.last_insert_rowid();
+
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO recognition_results (recognition_id, rank, label, confidence)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for (rank, result) in entry.results.iter().enumerate() {
+                insert.execute(params![id, rank as i64, result.label, result.confidence])?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(id)
+    }
+
+    // Newest first
+    pub fn list(&self, offset: u32, limit: u32) -> Result<HistoryPage> {
+        self.page(None, offset, limit)
+    }
+
+    // Entries with any result whose label contains `label`, case-insensitively
+    pub fn search(&self, label: &str, offset: u32, limit: u32) -> Result<HistoryPage> {
+        self.page(Some(&label_pattern(label)), offset, limit)
+    }
+
+    // Every entry, optionally filtered like `search`, newest first
+    pub fn entries(&self, label: Option<&str>) -> Result<Vec<HistoryEntry>> {
+        let pattern = label.map(label_pattern);
+        let conn = self.lock()?;
+        // A negative LIMIT means no limit in SQLite
+        select_entries(&conn, pattern.as_deref(), -1, 0)
+    }
+
+    // Returns false if no entry had this id
+    pub fn delete(&self, id: i64) -> Result<bool> {
+        let conn = self.lock()?;
+        let deleted = conn.execute("DELETE FROM recognitions WHERE id = ?1", [id])?;
+        Ok(deleted > 0)
+    }
+
+    // Returns the number of entries removed
+    pub fn clear(&self) -> Result<usize> {
+        let conn = self.lock()?;
+        let deleted = conn.execute("DELETE FROM recognitions", [])?;
+        Ok(deleted)
+    }
+
+    fn page(&self, label_pattern: Option<&str>, offset: u32, limit: u32) -> Result<HistoryPage> {
+        let limit = limit.clamp(1, MAX_PAGE_SIZE);
+        let conn = self.lock()?;
+
+        let total: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM recognitions WHERE {}", LABEL_FILTER),
+            params![label_pattern],
+            |row| row.get(0),
+        )?;
+        let entries = select_entries(&conn, label_pattern, limit as i64, offset as i64)?;
+
+        Ok(HistoryPage {
+            entries,
+            total: total as u64,
+            offset,
+            limit,
+        })
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Connection>> {
+        self.conn
+            .lock()
+            .map_err(|_| anyhow!("History database lock poisoned"))
+    }
+}
+
+// Matches every entry when ?1 is NULL, otherwise entries with a result label LIKE ?1
+const LABEL_FILTER: &str = "?1 IS NULL OR id IN (
+    SELECT recognition_id FROM recognition_results WHERE label LIKE ?1 ESCAPE '\\'
+)";
+
+fn select_entries(
+    conn: &Connection,
+    label_pattern: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<HistoryEntry>> {
+    let mut entries = {
+        let mut query = conn.prepare(&format!(
+            "SELECT id, source, source_kind, thumbnail, created_at, model_id, decode_ms, inference_ms, cached
+             FROM recognitions WHERE {}
+             ORDER BY created_at DESC, id DESC
+             LIMIT ?2 OFFSET ?3",
+            LABEL_FILTER
+        ))?;
+        let rows = query.query_map(params![label_pattern, limit, offset], read_entry)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()?
+    };
+
+    for entry in entries.iter_mut() {
+        entry.results = load_results(conn, entry.id)?;
+    }
+
+    Ok(entries)
+}
+
+// Bring the schema up to the latest version
+fn migrate(conn: &mut Connection) -> Result<()> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let latest = MIGRATIONS.len() as i64;
+
+    if version > latest {
+        bail!(
+            "History database schema version {} is newer than supported version {}",
+            version,
+            latest
+        );
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)
+            .with_context(|| format!("Failed to apply history migration {}", index + 1))?;
+        tx.pragma_update(None, "user_version", index as i64 + 1)?;
+        tx.commit()?;
+        info!("Migrated history database to version {}", index + 1);
+    }
+
+    Ok(())
+}
+
+// Row of `recognitions`, results are loaded separately
+fn read_entry(row: &rusqlite::Row) -> rusqlite::Result<HistoryEntry> {
+    let source_kind: String = row.get(2)?;
+    let thumbnail: Option<Vec<u8>> = row.get(3)?;
+
+    Ok(HistoryEntry {
+        id: row.get(0)?,
+        source: row.get(1)?,
+        source_kind: SourceKind::parse(&source_kind),
+        thumbnail: thumbnail.map(|bytes| general_purpose::STANDARD.encode(bytes)),
+        created_at: row.get(4)?,
+        model_id: row.get(5)?,
+        results: Vec::new(),
+        decode_ms: row.get(6)?,
+        inference_ms: row.get(7)?,
+        cached: row.get(8)?,
+    })
+}
+
+fn load_results(conn: &Connection, recognition_id: i64) -> Result<Vec<RecognitionResult>> {
+    let mut query = conn.prepare_cached(
+        "SELECT label, confidence FROM recognition_results
+         WHERE recognition_id = ?1 ORDER BY rank",
+    )?;
+    let rows = query.query_map([recognition_id], |row| {
+        Ok(RecognitionResult {
+            label: row.get(0)?,
+            confidence: row.get(1)?,
+        })
+    })?;
+
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+// Substring pattern for LIKE, which is case-insensitive for ASCII
+fn label_pattern(label: &str) -> String {
+    format!("%{}%", escape_like(label.trim()))
+}
+
+// Match `%` and `_` literally in LIKE patterns
+fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(store: &HistoryStore, source: &str, labels: &[&str]) -> i64 {
+        let results: Vec<_> = labels
+            .iter()
+            .map(|label| RecognitionResult {
+                label: label.to_string(),
+                confidence: 0.5,
+            })
+            .collect();
+        store
+            .record(&NewHistoryEntry {
+                source,
+                source_kind: SourceKind::Path,
+                thumbnail: None,
+                model_id: "model",
+                results: &results,
+                decode_ms: 1.0,
+                inference_ms: 2.0,
+                cached: false,
+            })
+            .unwrap()
+    }
+
+    fn sources(page: HistoryPage) -> Vec<String> {
+        page.entries.into_iter().map(|entry| entry.source).collect()
+    }
+
+    #[test]
+    fn migrates_version_1_rows() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        conn.execute(
+            "INSERT INTO recognitions
+                (source, source_kind, created_at, model_id, decode_ms, inference_ms)
+             VALUES ('old.jpg', 'path', 1, 'model', 1.0, 2.0)",
+            [],
+        )
+        .unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        let version: i64 = conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len() as i64);
+
+        let store = HistoryStore {
+            conn: Mutex::new(conn),
+        };
+        let entries = store.entries(None).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].source, "old.jpg");
+        assert!(!entries[0].cached);
+    }
+
+    #[test]
+    fn rejects_newer_schema() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", MIGRATIONS.len() as i64 + 1)
+            .unwrap();
+
+        assert!(migrate(&mut conn).is_err());
+    }
+
+    #[test]
+    fn search_matches_wildcards_literally() {
+        let store = HistoryStore::open(Path::new(":memory:")).unwrap();
+        record(&store, "percent.jpg", &["100% cotton"]);
+        record(&store, "digits.jpg", &["1000 cotton"]);
+        record(&store, "underscore.jpg", &["snake_case"]);
+        record(&store, "space.jpg", &["snake case"]);
+
+        assert_eq!(
+            sources(store.search("0%", 0, 10).unwrap()),
+            vec!["percent.jpg"]
+        );
+        assert_eq!(
+            sources(store.search("e_c", 0, 10).unwrap()),
+            vec!["underscore.jpg"]
+        );
+        // Still a case-insensitive substring match
+        assert_eq!(
+            sources(store.search("COTTON", 0, 10).unwrap()),
+            vec!["digits.jpg", "percent.jpg"]
+        );
+    }
+}
@@ -0,0 +1,452 @@
+use crate::content_uri;
+use anyhow::{bail, Context, Result};
+use log::{info, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::path::BaseDirectory;
+use tauri::{AppHandle, Manager, Runtime};
+use tauri_plugin_dialog::{DialogExt, FilePath};
+use taurivision_core::hash::sha256_hex;
+use taurivision_core::model_integrity::{
+    read_signature_file, sidecar_path, CHECKSUM_EXTENSION, SIGNATURE_EXTENSION,
+};
+use taurivision_core::model_manager::model_id_from_path;
+use taurivision_core::model_package::{is_package, PACKAGE_EXTENSION};
+use taurivision_core::{EnsembleConfig, ModelFormat, ModelInfo, ModelManager, TrustedKeys};
+
+// Imported models live in the app data dir, one folder per model id
+const MODELS_DIR: &str = "models";
+const LABELS_FILE: &str = "labels.txt";
+// Model packages bring their labels, so they are installed alone
+const PACKAGE_FILE: &str = "model.tvmodel";
+
+// Ed25519 public keys in the app config dir, one base64 key per line. When
+// there are any, imported models only load with a signature by one of them.
+const TRUSTED_KEYS_FILE: &str = "trusted-model-keys.txt";
+
+// Ensembles created in the app, a JSON array of configs in the app config dir
+const ENSEMBLES_FILE: &str = "ensembles.json";
+
+// Shipped through `bundle.resources` in tauri.conf.json
+const BUNDLED_MODEL: &str = "assets/model/mobilenet_v2.onnx";
+const BUNDLED_LABELS: &str = "assets/model/labels.txt";
+
+// A model and labels file pair on disk. For packages the labels path is
+// unused, since the labels are inside the package.
+pub struct ModelSource {
+    pub id: String,
+    pub model_path: PathBuf,
+    pub labels_path: PathBuf,
+}
+
+// Register the bundled model, every imported model and then the saved
+// ensembles of them. The bundled model becomes the default; returns the
+// number of models registered.
+pub fn register_discovered<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    model_manager: &mut ModelManager,
+) -> usize {
+    let mut registered = 0;
+
+    // Shipped with a checksum, so a corrupt install fails with a precise error
+    if let Some(source) = bundled_model(app_handle) {
+        match model_manager.register_verified(&source.id, &source.model_path, &source.labels_path) {
+            Ok(_) => registered += 1,
+            Err(e) => warn!("Skipping bundled model {}: {:#}", source.id, e),
+        }
+    }
+
+    let imported = imported_models(app_handle).and_then(|imported| {
+        let trusted_keys = trusted_keys(app_handle)?;
+        Ok((imported, trusted_keys))
+    });
+    match imported {
+        Ok((imported, trusted_keys)) => {
+            for source in imported {
+                match register_imported(model_manager, &source, trusted_keys.as_ref()) {
+                    Ok(_) => registered += 1,
+                    Err(e) => warn!("Skipping model {}: {:#}", source.id, e),
+                }
+            }
+        }
+        Err(e) => warn!("Failed to load imported models: {:#}", e),
+    }
+
+    match saved_ensembles(app_handle) {
+        Ok(ensembles) => {
+            for config in ensembles {
+                match model_manager.register_ensemble(&config) {
+                    Ok(_) => registered += 1,
+                    Err(e) => warn!("Skipping ensemble {}: {:#}", config.id, e),
+                }
+            }
+        }
+        Err(e) => warn!("Failed to load ensembles: {:#}", e),
+    }
+
+    registered
+}
+
+// Keys from the trusted keys file; None if signatures are not enforced
+pub fn trusted_keys<R: Runtime>(app_handle: &AppHandle<R>) -> Result<Option<TrustedKeys>> {
+    let path = app_handle.path().app_config_dir()?.join(TRUSTED_KEYS_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let contents =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+    let keys = TrustedKeys::parse(&contents).with_context(|| format!("In {:?}", path))?;
+    Ok((!keys.is_empty()).then_some(keys))
+}
+
+// Imported models are checked against the checksum written at install time
+// (models imported before checksums were written have none), and against
+// their signature when trusted keys are configured
+pub fn register_imported(
+    model_manager: &mut ModelManager,
+    source: &ModelSource,
+    trusted_keys: Option<&TrustedKeys>,
+) -> Result<ModelInfo> {
+    match trusted_keys {
+        Some(trusted_keys) => model_manager.register_signed(
+            &source.id,
+            &source.model_path,
+            &source.labels_path,
+            trusted_keys,
+        ),
+        None => model_manager.register(&source.id, &source.model_path, &source.labels_path),
+    }
+}
+
+fn ensembles_path<R: Runtime>(app_handle: &AppHandle<R>) -> Result<PathBuf> {
+    Ok(app_handle.path().app_config_dir()?.join(ENSEMBLES_FILE))
+}
+
+fn saved_ensembles<R: Runtime>(app_handle: &AppHandle<R>) -> Result<Vec<EnsembleConfig>> {
+    let path = ensembles_path(app_handle)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let contents =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+    serde_json::from_str(&contents).with_context(|| format!("Invalid ensembles file {:?}", path))
+}
+
+fn write_ensembles<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    ensembles: &[EnsembleConfig],
+) -> Result<()> {
+    let path = ensembles_path(app_handle)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(ensembles)?)
+        .with_context(|| format!("Failed to write {:?}", path))
+}
+
+// Keep an ensemble for the next start, replacing a saved one with the same id
+pub fn save_ensemble<R: Runtime>(app_handle: &AppHandle<R>, config: &EnsembleConfig) -> Result<()> {
+    let mut ensembles = saved_ensembles(app_handle)?;
+    ensembles.retain(|saved| saved.id != config.id);
+    ensembles.push(config.clone());
+    write_ensembles(app_handle, &ensembles)
+}
+
+// Returns whether an ensemble with that id was saved
+pub fn forget_ensemble<R: Runtime>(app_handle: &AppHandle<R>, model_id: &str) -> Result<bool> {
+    let mut ensembles = saved_ensembles(app_handle)?;
+    let count = ensembles.len();
+    ensembles.retain(|saved| saved.id != model_id);
+    if ensembles.len() == count {
+        return Ok(false);
+    }
+    write_ensembles(app_handle, &ensembles)?;
+    Ok(true)
+}
+
+// Resource paths are plain files on desktop. On Android they point into the
+// APK, which std::fs cannot read, so the embedded copy is used there instead.
+fn bundled_model<R: Runtime>(app_handle: &AppHandle<R>) -> Option<ModelSource> {
+    let resolver = app_handle.path();
+    let model_path = resolver
+        .resolve(BUNDLED_MODEL, BaseDirectory::Resource)
+        .ok()?;
+    let labels_path = resolver
+        .resolve(BUNDLED_LABELS, BaseDirectory::Resource)
+        .ok()?;
+
+    if !model_path.is_file() {
+        info!("No bundled model at {:?}", model_path);
+        return None;
+    }
+
+    Some(ModelSource {
+        id: model_id_from_path(&model_path),
+        model_path,
+        labels_path,
+    })
+}
+
+fn models_dir<R: Runtime>(app_handle: &AppHandle<R>) -> Result<PathBuf> {
+    Ok(app_handle.path().app_data_dir()?.join(MODELS_DIR))
+}
+
+// Folders of the models dir holding a model file, sorted by id
+fn imported_models<R: Runtime>(app_handle: &AppHandle<R>) -> Result<Vec<ModelSource>> {
+    let dir = models_dir(app_handle)?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut sources = Vec::new();
+    for entry in fs::read_dir(&dir).with_context(|| format!("Failed to read {:?}", dir))? {
+        let model_dir = entry?.path();
+        let Some(id) = model_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+        else {
+            continue;
+        };
+        if !model_dir.is_dir() || id.starts_with('.') {
+            continue;
+        }
+
+        match find_model_file(&model_dir) {
+            Some(model_path) => sources.push(ModelSource {
+                id,
+                model_path,
+                labels_path: model_dir.join(LABELS_FILE),
+            }),
+            None => warn!("No model file in {:?}", model_dir),
+        }
+    }
+
+    sources.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(sources)
+}
+
+fn find_model_file(model_dir: &Path) -> Option<PathBuf> {
+    std::iter::once(PACKAGE_FILE)
+        .chain([ModelFormat::Onnx, ModelFormat::Tflite].map(model_file_name))
+        .map(|name| model_dir.join(name))
+        .find(|path| path.is_file())
+}
+
+fn model_file_name(format: ModelFormat) -> &'static str {
+    match format {
+        ModelFormat::Onnx => "model.onnx",
+        ModelFormat::Tflite => "model.tflite",
+    }
+}
+
+// Model and labels picked by the user, not yet installed
+pub struct PickedModel {
+    // File stem of the picked model, the default model id
+    pub name: String,
+    pub format: ModelFormat,
+    model_bytes: Vec<u8>,
+    // None for model packages
+    labels_bytes: Option<Vec<u8>>,
+    // Contents of a .sig file found next to the picked model
+    signature: Option<String>,
+}
+
+impl PickedModel {
+    fn file_name(&self) -> &'static str {
+        if self.labels_bytes.is_none() {
+            PACKAGE_FILE
+        } else {
+            model_file_name(self.format)
+        }
+    }
+}
+
+// Ask for a model file, then its labels file unless the model is a package;
+// None if a dialog was cancelled
+pub async fn pick_model_files<R: Runtime>(
+    app_handle: &AppHandle<R>,
+) -> Result<Option<PickedModel>> {
+    let extensions = ["onnx", "tflite", PACKAGE_EXTENSION, "zip"];
+    let Some(model_file) = pick_file(app_handle, "Model", &extensions).await? else {
+        return Ok(None);
+    };
+    let model_name = picked_name(&model_file);
+    let (model_bytes, signature) = tokio::task::spawn_blocking(move || {
+        Ok::<_, anyhow::Error>((read_picked(&model_file)?, picked_signature(&model_file)?))
+    })
+    .await??;
+
+    let labels_bytes = if is_package(&model_bytes) {
+        None
+    } else {
+        let Some(labels_file) = pick_file(app_handle, "Labels", &["txt"]).await? else {
+            return Ok(None);
+        };
+        Some(tokio::task::spawn_blocking(move || read_picked(&labels_file)).await??)
+    };
+
+    let format = ModelFormat::detect(Some(Path::new(&model_name)), &model_bytes);
+    Ok(Some(PickedModel {
+        name: sanitize_model_id(&model_id_from_path(Path::new(&model_name))),
+        format,
+        model_bytes,
+        labels_bytes,
+        signature,
+    }))
+}
+
+async fn pick_file<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    filter_name: &str,
+    extensions: &[&str],
+) -> Result<Option<FilePath>> {
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+
+    app_handle
+        .dialog()
+        .file()
+        .add_filter(filter_name, extensions)
+        .pick_file(move |path| {
+            let _ = sender.send(path);
+        });
+
+    receiver.await.context("File dialog closed unexpectedly")
+}
+
+// Android hands out content URIs, desktop platforms plain paths
+fn read_picked(file: &FilePath) -> Result<Vec<u8>> {
+    match file {
+        FilePath::Path(path) => {
+            fs::read(path).with_context(|| format!("Failed to read {:?}", path))
+        }
+        FilePath::Url(url) if url.scheme() == "file" => {
+            let path = url
+                .to_file_path()
+                .map_err(|_| anyhow::anyhow!("Invalid file URL: {}", url))?;
+            fs::read(&path).with_context(|| format!("Failed to read {:?}", path))
+        }
+        FilePath::Url(url) => content_uri::read_content_uri(url.as_str()),
+    }
+}
+
+// Content URIs have no siblings to look at, so signatures only come along
+// for files picked by path
+fn picked_signature(file: &FilePath) -> Result<Option<String>> {
+    let path = match file {
+        FilePath::Path(path) => path.clone(),
+        FilePath::Url(url) if url.scheme() == "file" => match url.to_file_path() {
+            Ok(path) => path,
+            Err(_) => return Ok(None),
+        },
+        FilePath::Url(_) => return Ok(None),
+    };
+    Ok(read_signature_file(&path)?)
+}
+
+fn picked_name(file: &FilePath) -> String {
+    match file {
+        FilePath::Path(path) => path.to_string_lossy().into_owned(),
+        FilePath::Url(url) => url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .unwrap_or_default()
+            .to_string(),
+    }
+}
+
+// Copy a picked model into the models dir under `model_id`
+pub fn install<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    model_id: &str,
+    picked: &PickedModel,
+) -> Result<ModelSource> {
+    validate_model_id(model_id)?;
+
+    let model_dir = models_dir(app_handle)?.join(model_id);
+    if model_dir.exists() {
+        bail!("A model named {} is already imported", model_id);
+    }
+
+    // Written under a hidden name first, so discovery never sees half a model
+    let staging_dir = model_dir.with_file_name(format!(".{}.import", model_id));
+    let _ = fs::remove_dir_all(&staging_dir);
+    fs::create_dir_all(&staging_dir)
+        .with_context(|| format!("Failed to create {:?}", staging_dir))?;
+    let model_path = staging_dir.join(picked.file_name());
+    fs::write(&model_path, &picked.model_bytes)?;
+    // Lets later loads notice the file changing on disk after the import
+    fs::write(
+        sidecar_path(&model_path, CHECKSUM_EXTENSION),
+        format!(
+            "{}  {}\n",
+            sha256_hex(&picked.model_bytes),
+            picked.file_name()
+        ),
+    )?;
+    if let Some(signature) = &picked.signature {
+        fs::write(sidecar_path(&model_path, SIGNATURE_EXTENSION), signature)?;
+    }
+    if let Some(labels_bytes) = &picked.labels_bytes {
+        fs::write(staging_dir.join(LABELS_FILE), labels_bytes)?;
+    }
+    fs::rename(&staging_dir, &model_dir)
+        .with_context(|| format!("Failed to install model into {:?}", model_dir))?;
+
+    info!("Imported model {} into {:?}", model_id, model_dir);
+    Ok(ModelSource {
+        id: model_id.to_string(),
+        model_path: model_dir.join(picked.file_name()),
+        labels_path: model_dir.join(LABELS_FILE),
+    })
+}
+
+// Remove an imported model's files, e.g. after it failed to load
+pub fn uninstall<R: Runtime>(app_handle: &AppHandle<R>, model_id: &str) {
+    if validate_model_id(model_id).is_err() {
+        return;
+    }
+
+    if let Ok(dir) = models_dir(app_handle) {
+        if let Err(e) = fs::remove_dir_all(dir.join(model_id)) {
+            warn!("Failed to remove model {}: {}", model_id, e);
+        }
+    }
+}
+
+fn sanitize_model_id(name: &str) -> String {
+    let id: String = name
+        .trim_start_matches('.')
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if id.is_empty() {
+        "imported".to_string()
+    } else {
+        id
+    }
+}
+
+// Model ids become folder names, so they must not be able to name other paths
+fn validate_model_id(model_id: &str) -> Result<()> {
+    let valid = !model_id.is_empty()
+        && !model_id.starts_with('.')
+        && model_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if !valid {
+        bail!(
+            "Invalid model id {:?}: use letters, digits, '_', '-' and '.'",
+            model_id
+        );
+    }
+    Ok(())
+}
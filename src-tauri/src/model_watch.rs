@@ -0,0 +1,148 @@
+use anyhow::{Context, Result};
+use log::{info, warn};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Runtime};
+use taurivision_core::{ModelInfo, ModelManager};
+use tokio::sync::{mpsc, Mutex};
+
+// Emitted with the new ModelInfo after a watched model was rebuilt and swapped in
+pub const MODEL_RELOADED_EVENT: &str = "model-reloaded";
+// Emitted with a ReloadFailure; the previous model stays registered
+pub const MODEL_RELOAD_FAILED_EVENT: &str = "model-reload-failed";
+
+// Exporters write a model in several steps, so wait for the files to settle
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ReloadFailure {
+    pub model_id: String,
+    pub error: String,
+}
+
+// Watches the files of one model, or of the members of an ensemble, and
+// reloads a model when its files change. Dropping it stops watching.
+pub struct ModelWatch {
+    _watcher: RecommendedWatcher,
+    task: JoinHandle<()>,
+}
+
+impl Drop for ModelWatch {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+// Start watching the files of each model in `sources`, given as model id and
+// the files it was loaded from. Ensembles follow their members as they reload.
+pub fn start<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    model_manager: Arc<Mutex<ModelManager>>,
+    sources: &[(String, Vec<PathBuf>)],
+) -> Result<ModelWatch> {
+    // Event paths are absolute, while models may be registered by relative path
+    let watched: HashMap<PathBuf, String> = sources
+        .iter()
+        .flat_map(|(model_id, paths)| {
+            paths
+                .iter()
+                .filter_map(|path| absolute(path))
+                .map(move |path| (path, model_id.clone()))
+        })
+        .collect();
+    // Editors and exporters often replace files by renaming over them, which
+    // a watch on the file itself would not survive
+    let dirs: HashSet<PathBuf> = watched
+        .keys()
+        .filter_map(|path| path.parent().map(Path::to_path_buf))
+        .collect();
+
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event) if event.kind.is_access() => {}
+            Ok(event) => {
+                for model_id in event.paths.iter().filter_map(|path| watched.get(path)) {
+                    let _ = sender.send(model_id.clone());
+                }
+            }
+            Err(e) => warn!("Model watcher error: {}", e),
+        })
+        .context("Failed to create file watcher")?;
+    for dir in &dirs {
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Failed to watch {:?}", dir))?;
+    }
+
+    let app_handle = app_handle.clone();
+    let model_ids: Vec<&str> = sources
+        .iter()
+        .map(|(model_id, _)| model_id.as_str())
+        .collect();
+    info!("Watching models {:?} for changes in {:?}", model_ids, dirs);
+    let task = tauri::async_runtime::spawn(async move {
+        while let Some(model_id) = receiver.recv().await {
+            // Collapse the burst of events a single export produces
+            let mut changed = BTreeSet::from([model_id]);
+            while let Ok(Some(model_id)) = tokio::time::timeout(DEBOUNCE, receiver.recv()).await {
+                changed.insert(model_id);
+            }
+            for model_id in &changed {
+                reload(&app_handle, &model_manager, model_id).await;
+            }
+        }
+    });
+
+    Ok(ModelWatch {
+        _watcher: watcher,
+        task,
+    })
+}
+
+async fn reload<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    model_manager: &Mutex<ModelManager>,
+    model_id: &str,
+) {
+    let emitted = match rebuild(model_manager, model_id).await {
+        Ok(info) => {
+            info!("Reloaded model {} ({})", model_id, info.sha256);
+            app_handle.emit(MODEL_RELOADED_EVENT, info)
+        }
+        Err(e) => {
+            warn!("Failed to reload model {}: {:#}", model_id, e);
+            let failure = ReloadFailure {
+                model_id: model_id.to_string(),
+                error: format!("{:#}", e),
+            };
+            app_handle.emit(MODEL_RELOAD_FAILED_EVENT, failure)
+        }
+    };
+
+    if let Err(e) = emitted {
+        warn!("Failed to emit model reload event: {}", e);
+    }
+}
+
+async fn rebuild(model_manager: &Mutex<ModelManager>, model_id: &str) -> Result<ModelInfo> {
+    let job = model_manager.lock().await.reload_job(model_id)?;
+    // Built without holding the lock, so recognition keeps using the old model meanwhile
+    let prepared = tokio::task::spawn_blocking(move || job.run()).await??;
+    model_manager.lock().await.finish_reload(prepared)
+}
+
+// Canonical parent joined with the file name, so files that do not exist yet still resolve
+fn absolute(path: &Path) -> Option<PathBuf> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    Some(fs::canonicalize(parent).ok()?.join(path.file_name()?))
+}
@@ -0,0 +1,233 @@
+use anyhow::{bail, Result};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, Runtime};
+use taurivision_core::organize::{self, ApplyReport, FileFailure, OrganizeMode, OrganizePlan};
+
+// Stored plans and undo journals live in the app data dir, out of the user's
+// photo folders. A plan and the journal of applying it share one id.
+const JOURNAL_DIR: &str = "organize-journals";
+const PLAN_EXTENSION: &str = "plan.json";
+const JOURNAL_EXTENSION: &str = "jsonl";
+
+// Previews that were never applied are dropped after this long
+const PLAN_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct OrganizeOptions {
+    // Root of the bucket folders, the organized directory itself if unset
+    pub destination: Option<String>,
+    pub mode: OrganizeMode,
+    // Roll labels up into categories, see `CategoryMap`
+    pub category_map: Option<String>,
+    // Images whose top label is below this go to the "uncertain" bucket
+    pub min_confidence: f32,
+    pub recursive: bool,
+}
+
+impl Default for OrganizeOptions {
+    fn default() -> Self {
+        Self {
+            destination: None,
+            mode: OrganizeMode::Move,
+            category_map: None,
+            min_confidence: 0.5,
+            recursive: false,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct OrganizeResult {
+    // Pass to `apply_organize` to carry out this plan
+    pub plan_id: String,
+    pub plan: OrganizePlan,
+    // Images that could not be recognized and stay where they are
+    pub skipped: Vec<FileFailure>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct AppliedOrganize {
+    // Pass to `undo_organize` to revert the run
+    pub journal_id: String,
+    pub applied: ApplyReport,
+}
+
+// Store a previewed plan and return its id
+pub fn store_plan<R: Runtime>(app_handle: &AppHandle<R>, plan: &OrganizePlan) -> Result<String> {
+    // Ids only need to be unique within this app data dir
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or(0);
+    let plan_id = format!(
+        "organize-{}-{}",
+        millis,
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+
+    let dir = journal_dir(app_handle)?;
+    prune_plans(&dir);
+    plan.save(&run_file(&dir, &plan_id, PLAN_EXTENSION)?)?;
+    Ok(plan_id)
+}
+
+// Carry out a stored plan once; the plan is consumed and its journal kept for undo
+pub fn apply_stored_plan<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    plan_id: &str,
+) -> Result<ApplyReport> {
+    let dir = journal_dir(app_handle)?;
+    apply_plan_in(&dir, plan_id)
+}
+
+// Journal ids come from the frontend, so they must not be able to name other files
+pub fn journal_path<R: Runtime>(app_handle: &AppHandle<R>, journal_id: &str) -> Result<PathBuf> {
+    run_file(&journal_dir(app_handle)?, journal_id, JOURNAL_EXTENSION)
+}
+
+fn journal_dir<R: Runtime>(app_handle: &AppHandle<R>) -> Result<PathBuf> {
+    Ok(app_handle.path().app_data_dir()?.join(JOURNAL_DIR))
+}
+
+fn apply_plan_in(dir: &Path, plan_id: &str) -> Result<ApplyReport> {
+    let plan_path = run_file(dir, plan_id, PLAN_EXTENSION)?;
+    let plan = match OrganizePlan::load(&plan_path) {
+        Ok(plan) => plan,
+        Err(e) if is_not_found(&e) => bail!("Unknown or already applied plan: {}", plan_id),
+        Err(e) => return Err(e),
+    };
+
+    // The journal is created exclusively, so a plan cannot be applied twice
+    let report = organize::apply_plan(&plan, &run_file(dir, plan_id, JOURNAL_EXTENSION)?)?;
+    if let Err(e) = fs::remove_file(&plan_path) {
+        warn!("Failed to remove applied plan {:?}: {}", plan_path, e);
+    }
+    Ok(report)
+}
+
+// `<id>.<extension>` in `dir`, for ids of the form `organize-123-0`
+fn run_file(dir: &Path, id: &str, extension: &str) -> Result<PathBuf> {
+    let valid = !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if !valid {
+        bail!("Invalid journal id: {}", id);
+    }
+
+    Ok(dir.join(format!("{}.{}", id, extension)))
+}
+
+fn prune_plans(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let suffix = format!(".{}", PLAN_EXTENSION);
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.to_string_lossy().ends_with(&suffix) {
+            continue;
+        }
+        let expired = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > PLAN_MAX_AGE);
+        if expired {
+            if let Err(e) = fs::remove_file(&path) {
+                warn!("Failed to remove expired plan {:?}: {}", path, e);
+            }
+        }
+    }
+}
+
+fn is_not_found(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<std::io::Error>()
+        .is_some_and(|e| e.kind() == ErrorKind::NotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use taurivision_core::organize::{plan_organize, OrganizeItem};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "taurivision-organizer-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn rejects_ids_naming_other_files() {
+        let dir = Path::new("journals");
+
+        assert_eq!(
+            run_file(dir, "organize-1-0", JOURNAL_EXTENSION).unwrap(),
+            dir.join("organize-1-0.jsonl")
+        );
+        for id in ["", "../history", "a/b", "a\\b", "plan.json", "organize 1"] {
+            assert!(run_file(dir, id, JOURNAL_EXTENSION).is_err(), "{:?}", id);
+        }
+    }
+
+    #[test]
+    fn applies_stored_plan_and_undoes_it() {
+        let root = temp_dir("round-trip");
+        let photos = root.join("photos");
+        let journals = root.join("journals");
+        fs::create_dir_all(&photos).unwrap();
+        fs::write(photos.join("a.jpg"), b"a").unwrap();
+        fs::write(photos.join("b.jpg"), b"b").unwrap();
+
+        let items = [
+            OrganizeItem {
+                path: photos.join("a.jpg"),
+                top: Some(("cat".to_string(), 0.9)),
+            },
+            OrganizeItem {
+                path: photos.join("b.jpg"),
+                top: Some(("dog".to_string(), 0.2)),
+            },
+        ];
+        let plan = plan_organize(&items, &photos, OrganizeMode::Move, None, 0.5);
+        plan.save(&run_file(&journals, "organize-1-0", PLAN_EXTENSION).unwrap())
+            .unwrap();
+
+        // Files added after the preview are not part of the stored plan
+        fs::write(photos.join("c.jpg"), b"c").unwrap();
+
+        let report = apply_plan_in(&journals, "organize-1-0").unwrap();
+        assert_eq!(report.completed, 2);
+        assert!(report.failures.is_empty());
+        assert_eq!(fs::read(photos.join("cat/a.jpg")).unwrap(), b"a");
+        assert_eq!(fs::read(photos.join("uncertain/b.jpg")).unwrap(), b"b");
+        assert!(photos.join("c.jpg").exists());
+
+        // The plan is consumed
+        assert!(apply_plan_in(&journals, "organize-1-0").is_err());
+
+        let journal = run_file(&journals, "organize-1-0", JOURNAL_EXTENSION).unwrap();
+        let undone = organize::undo(&journal).unwrap();
+        assert_eq!(undone.restored, 2);
+        assert!(undone.failures.is_empty());
+        assert_eq!(fs::read(photos.join("a.jpg")).unwrap(), b"a");
+        assert_eq!(fs::read(photos.join("b.jpg")).unwrap(), b"b");
+        assert!(!photos.join("cat").exists());
+        assert!(!photos.join("uncertain").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
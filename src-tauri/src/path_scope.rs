@@ -0,0 +1,194 @@
+use log::warn;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, Runtime};
+use tauri_plugin_fs::FsExt;
+use taurivision_core::scan;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum PathScopeError {
+    #[error("Failed to resolve path {path}: {source}")]
+    Resolve {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("Permission denied: {0} is outside the allowed file scope")]
+    PermissionDenied(String),
+
+    #[error("Failed to scan {path}: {message}")]
+    Scan { path: String, message: String },
+}
+
+// Directories the backend may read from without the user picking a file first.
+// Files and folders picked through the dialog plugin are added to the same scope.
+pub fn allow_default_scopes<R: Runtime>(app_handle: &AppHandle<R>) -> tauri::Result<()> {
+    let scope = app_handle.fs_scope();
+    let paths = app_handle.path();
+
+    let app_data_dir = paths.app_data_dir()?;
+    std::fs::create_dir_all(&app_data_dir)?;
+    scope.allow_directory(canonical_or_original(&app_data_dir), true)?;
+
+    // Gallery location, not available on every platform
+    if let Ok(picture_dir) = paths.picture_dir() {
+        scope.allow_directory(canonical_or_original(&picture_dir), true)?;
+    }
+
+    Ok(())
+}
+
+// Resolve a frontend-supplied path and require it to stay inside the fs scope.
+// Symlinks are resolved first, so a link inside the scope cannot point outside it.
+pub fn resolve_scoped_path<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    path: &str,
+) -> Result<PathBuf, PathScopeError> {
+    let scope = app_handle.fs_scope();
+    resolve_within(path, |resolved| scope.is_allowed(resolved))
+}
+
+fn resolve_within(
+    path: &str,
+    is_allowed: impl Fn(&Path) -> bool,
+) -> Result<PathBuf, PathScopeError> {
+    let resolved = std::fs::canonicalize(path).map_err(|source| PathScopeError::Resolve {
+        path: path.to_string(),
+        source,
+    })?;
+
+    if !is_allowed(&resolved) {
+        warn!("Rejected path outside of scope: {} ({:?})", path, resolved);
+        return Err(PathScopeError::PermissionDenied(path.to_string()));
+    }
+
+    Ok(resolved)
+}
+
+// Expand files and folders into scoped image paths. Every file found in a
+// folder is checked on its own, so links out of the scope are skipped.
+pub fn resolve_scoped_images<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    paths: &[String],
+    recursive: bool,
+) -> Result<Vec<PathBuf>, PathScopeError> {
+    let mut images = Vec::new();
+
+    for path in paths {
+        let resolved = resolve_scoped_path(app_handle, path)?;
+        if !resolved.is_dir() {
+            images.push(resolved);
+            continue;
+        }
+
+        let found = scan::scan_dir(&resolved, recursive).map_err(|e| PathScopeError::Scan {
+            path: path.clone(),
+            message: format!("{:#}", e),
+        })?;
+        for image in found {
+            match resolve_scoped_path(app_handle, &image.to_string_lossy()) {
+                Ok(image) => images.push(image),
+                Err(e) => warn!("Skipping {:?}: {}", image, e),
+            }
+        }
+    }
+
+    images.sort();
+    images.dedup();
+    Ok(images)
+}
+
+// Scope patterns are matched against canonical paths
+fn canonical_or_original(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // An allowed directory and a sibling outside of it, each with one file
+    struct Dirs {
+        root: PathBuf,
+        allowed: PathBuf,
+        outside: PathBuf,
+    }
+
+    impl Dirs {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!(
+                "taurivision-path-scope-{}-{}",
+                std::process::id(),
+                name
+            ));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(root.join("allowed")).unwrap();
+            fs::create_dir_all(root.join("outside")).unwrap();
+            fs::write(root.join("allowed/photo.jpg"), b"inside").unwrap();
+            fs::write(root.join("outside/secret.jpg"), b"outside").unwrap();
+
+            // Scope patterns are canonical, e.g. /tmp is a link on macOS
+            let root = fs::canonicalize(&root).unwrap();
+            Self {
+                allowed: root.join("allowed"),
+                outside: root.join("outside"),
+                root,
+            }
+        }
+
+        fn resolve(&self, path: &Path) -> Result<PathBuf, PathScopeError> {
+            resolve_within(&path.to_string_lossy(), |resolved| {
+                resolved.starts_with(&self.allowed)
+            })
+        }
+    }
+
+    impl Drop for Dirs {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    #[test]
+    fn allows_paths_inside_the_scope() {
+        let dirs = Dirs::new("inside");
+
+        let resolved = dirs.resolve(&dirs.allowed.join("photo.jpg")).unwrap();
+        assert_eq!(resolved, dirs.allowed.join("photo.jpg"));
+    }
+
+    #[test]
+    fn rejects_parent_dir_escape() {
+        let dirs = Dirs::new("parent");
+
+        let escape = dirs.allowed.join("../outside/secret.jpg");
+        assert!(matches!(
+            dirs.resolve(&escape),
+            Err(PathScopeError::PermissionDenied(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_symlink_out_of_scope() {
+        let dirs = Dirs::new("symlink");
+        let link = dirs.allowed.join("link.jpg");
+        std::os::unix::fs::symlink(dirs.outside.join("secret.jpg"), &link).unwrap();
+
+        assert!(matches!(
+            dirs.resolve(&link),
+            Err(PathScopeError::PermissionDenied(_))
+        ));
+    }
+
+    #[test]
+    fn reports_missing_paths() {
+        let dirs = Dirs::new("missing");
+
+        assert!(matches!(
+            dirs.resolve(&dirs.allowed.join("missing.jpg")),
+            Err(PathScopeError::Resolve { .. })
+        ));
+    }
+}
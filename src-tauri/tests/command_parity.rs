@@ -0,0 +1,56 @@
+// Desktop (main.rs) and mobile (the mobile_entry_point on `run`) both start
+// through `taurivision_lib::run()`, and the IPC handler is expanded from the
+// same list as `COMMANDS`. These checks read the sources rather than invoking
+// commands, which would run them against real app data.
+use std::collections::BTreeSet;
+
+const LIB: &str = include_str!("../src/lib.rs");
+const MAIN: &str = include_str!("../src/main.rs");
+const COMMANDS_RS: &str = include_str!("../src/commands.rs");
+
+// Names of the `#[tauri::command]` functions in a source file
+fn command_fns(source: &str) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    let mut lines = source.lines();
+    while let Some(line) = lines.next() {
+        if line.trim() != "#[tauri::command]" {
+            continue;
+        }
+        let signature = lines
+            .by_ref()
+            .find(|line| line.contains("fn "))
+            .expect("command attribute without a function");
+        let name = signature.split("fn ").nth(1).unwrap();
+        let end = name.find(['<', '(']).unwrap_or(name.len());
+        names.insert(name[..end].to_string());
+    }
+    names
+}
+
+#[test]
+fn commands_are_unique() {
+    let unique: BTreeSet<_> = taurivision_lib::COMMANDS.iter().collect();
+    assert_eq!(unique.len(), taurivision_lib::COMMANDS.len());
+}
+
+#[test]
+fn every_command_fn_is_registered() {
+    let registered: BTreeSet<String> = taurivision_lib::COMMANDS
+        .iter()
+        .map(|command| command.to_string())
+        .collect();
+    assert_eq!(command_fns(COMMANDS_RS), registered);
+}
+
+#[test]
+fn desktop_and_mobile_share_the_builder() {
+    assert!(MAIN.contains("taurivision_lib::run()"));
+
+    let run = LIB
+        .split("#[cfg_attr(mobile, tauri::mobile_entry_point)]")
+        .nth(1)
+        .expect("run() is not the mobile entry point");
+    assert!(run.trim_start().starts_with("pub fn run()"));
+    assert!(run.contains("app_builder(tauri::Builder::default())"));
+    assert!(LIB.contains(".invoke_handler(invoke_handler())"));
+}
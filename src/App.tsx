@@ -46,7 +46,7 @@ function App() {
   }, []);
 
 
-  const handleImageSelected = async (imagePath: string, imageData?: string) => {
+  const handleImageSelected = async (imagePath: string, _imageData?: string) => {
     if (!modelInitialized) {
       setErrorMessage('Model not initialized. Please wait and try again.');
       return;
@@ -59,12 +59,10 @@ function App() {
 
       let recognitionResults: RecognitionResult[];
 
-      if (imageData && imagePath.startsWith('content://')) {
-        // For content URIs, use the image data
-        const base64Data = imageData.split(',')[1]; // Remove the "data:image/jpeg;base64," part
-
-        recognitionResults = await invoke('recognize_image_data', {
-          imageData: base64Data
+      if (imagePath.startsWith('content://')) {
+        // For content URIs, let the backend read the stream directly
+        recognitionResults = await invoke('recognize_content_uri', {
+          uri: imagePath
         });
       } else {
         // For regular file paths, use the path
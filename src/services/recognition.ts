@@ -1,10 +1,232 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 
 export interface RecognitionResult {
   label: string;
   confidence: number;
 }
 
+export interface HistoryEntry {
+  id: number;
+  source: string;
+  source_kind: 'path' | 'content_uri' | 'data';
+  thumbnail: string | null;
+  created_at: number;
+  model_id: string;
+  results: RecognitionResult[];
+  decode_ms: number;
+  inference_ms: number;
+  cached: boolean;
+}
+
+export interface HistoryPage {
+  entries: HistoryEntry[];
+  total: number;
+  offset: number;
+  limit: number;
+}
+
+export interface CacheStats {
+  memory_hits: number;
+  disk_hits: number;
+  misses: number;
+  memory_entries: number;
+  memory_capacity: number;
+  disk_enabled: boolean;
+  disk_entries: number;
+}
+
+export type ExportFormat = 'csv' | 'jsonl' | 'coco';
+
+export interface TagOptions {
+  target?: 'sidecar' | 'embedded';
+  min_confidence?: number;
+  max_keywords?: number;
+  recursive?: boolean;
+  dry_run?: boolean;
+  confirm_overwrite?: boolean;
+}
+
+export interface TagReport {
+  path: string;
+  status: 'planned' | 'written' | 'unchanged' | 'no_keywords' | 'needs_confirmation' | 'failed';
+  plan: {
+    image: string;
+    target: 'sidecar' | 'embedded';
+    file: string;
+    keywords: string[];
+    action: 'create' | 'update' | 'unchanged';
+    modifies_existing: boolean;
+  } | null;
+  error: string | null;
+}
+
+export interface ModelInfo {
+  id: string;
+  source: string;
+  num_labels: number;
+  input_shape: number[];
+  sha256: string;
+  format: 'onnx' | 'tflite';
+  precision: 'float' | 'int8';
+  manifest: ModelManifest | null;
+  // Set for ensembles, which combine other registered models
+  ensemble: EnsembleConfig | null;
+}
+
+export interface EnsembleMember {
+  model_id: string;
+  weight?: number;
+  // JSON file of ensemble label to member labels, for members with a different label space
+  label_map?: string | null;
+}
+
+export interface EnsembleConfig {
+  // Virtual model id the ensemble is registered under
+  id: string;
+  method?: 'average' | 'vote';
+  members: EnsembleMember[];
+}
+
+export interface ModelManifest {
+  manifest_version: number;
+  name: string;
+  version: string;
+  license: string | null;
+  description: string | null;
+  task: 'classification';
+  model_file: string;
+  input: {
+    width: number;
+    height: number;
+    layout: 'nchw' | 'nhwc' | null;
+  };
+  normalization: {
+    mean: [number, number, number];
+    std: [number, number, number];
+  };
+  postprocessing: {
+    activation: 'none' | 'softmax' | 'sigmoid';
+  };
+  checksum: {
+    sha256: string;
+  };
+}
+
+export interface TensorInfo {
+  name: string;
+  // Fixed sizes, or names of symbolic dimensions such as "batch_size"
+  shape: (number | string)[];
+  dtype: string;
+}
+
+export interface InspectionReport {
+  format: 'onnx' | 'tflite';
+  file_bytes: number;
+  sha256: string;
+  manifest: ModelManifest | null;
+  producer: string | null;
+  ir_version: number | null;
+  opsets: { domain: string; version: number }[];
+  inputs: TensorInfo[];
+  outputs: TensorInfo[];
+  node_count: number;
+  op_counts: Record<string, number>;
+  parameter_count: number;
+  parameter_bytes: number;
+  unsupported_ops: string[];
+  precision: 'float' | 'int8' | null;
+  load_error: string | null;
+}
+
+export interface CompareOptions {
+  top_k?: number;
+  recursive?: boolean;
+}
+
+export interface LatencyStats {
+  iterations: number;
+  mean_ms: number;
+  min_ms: number;
+  max_ms: number;
+  p50_ms: number;
+  p95_ms: number;
+  p99_ms: number;
+}
+
+export interface ComparedModel {
+  id: string;
+  sha256: string;
+  latency: LatencyStats;
+}
+
+export interface ImageDisagreement {
+  path: string;
+  a: RecognitionResult[];
+  b: RecognitionResult[];
+}
+
+export interface AbReport {
+  images: number;
+  failed: string[];
+  top_k: number;
+  model_a: ComparedModel;
+  model_b: ComparedModel;
+  // Share of images where both models agree on the top-1 label
+  agreement_rate: number;
+  // Share of images where each model's top-1 label is among the other's top-k
+  top1_overlap: number;
+  top_k_overlap: number;
+  // B's p50 latency minus A's; negative when B is faster
+  latency_diff_ms: number;
+  disagreements: ImageDisagreement[];
+}
+
+export interface ModelReloadFailure {
+  model_id: string;
+  error: string;
+}
+
+export interface OrganizeOptions {
+  destination?: string;
+  mode?: 'move' | 'copy';
+  category_map?: string;
+  min_confidence?: number;
+  recursive?: boolean;
+}
+
+export interface FileFailure {
+  path: string;
+  error: string;
+}
+
+export interface OrganizeResult {
+  plan_id: string;
+  plan: {
+    mode: 'move' | 'copy';
+    destination_root: string;
+    files: {
+      source: string;
+      destination: string;
+      bucket: string;
+      label: string | null;
+      confidence: number;
+    }[];
+    unchanged: string[];
+  };
+  skipped: FileFailure[];
+}
+
+export interface AppliedOrganize {
+  journal_id: string;
+  applied: { completed: number; failures: FileFailure[] };
+}
+
+export interface UndoReport {
+  restored: number;
+  failures: FileFailure[];
+}
+
 export class RecognitionService {
   private static instance: RecognitionService;
   private modelInitialized: boolean = false;
@@ -38,9 +260,10 @@ export class RecognitionService {
   /**
    * Recognize an image from its file path
    * @param imagePath Path to the image file
+   * @param modelId Registered model or ensemble to use, the default model if unset
    * @returns Array of recognition results
    */
-  public async recognizeImage(imagePath: string): Promise<RecognitionResult[]> {
+  public async recognizeImage(imagePath: string, modelId?: string): Promise<RecognitionResult[]> {
     if (!this.modelInitialized) {
       await this.initModel();
     }
@@ -48,6 +271,7 @@ export class RecognitionService {
     try {
       const results = await invoke<RecognitionResult[]>('recognize_image', {
         imagePath,
+        modelId,
       });
       return results;
     } catch (error) {
@@ -59,9 +283,10 @@ export class RecognitionService {
   /**
    * Recognize an image from its base64 encoded data
    * @param imageData Base64 encoded image data
+   * @param modelId Registered model or ensemble to use, the default model if unset
    * @returns Array of recognition results
    */
-  public async recognizeImageData(imageData: string): Promise<RecognitionResult[]> {
+  public async recognizeImageData(imageData: string, modelId?: string): Promise<RecognitionResult[]> {
     if (!this.modelInitialized) {
       await this.initModel();
     }
@@ -69,6 +294,7 @@ export class RecognitionService {
     try {
       const results = await invoke<RecognitionResult[]>('recognize_image_data', {
         imageData,
+        modelId,
       });
       return results;
     } catch (error) {
@@ -76,6 +302,219 @@ export class RecognitionService {
       throw error;
     }
   }
+
+  /**
+   * Recognize an image from its encoded bytes, sent as a raw IPC body
+   * @param imageBytes Encoded image file contents (JPEG, PNG, ...)
+   * @param modelId Registered model or ensemble to use, the default model if unset
+   * @returns Array of recognition results
+   */
+  public async recognizeImageBytes(
+    imageBytes: Uint8Array | ArrayBuffer,
+    modelId?: string,
+  ): Promise<RecognitionResult[]> {
+    if (!this.modelInitialized) {
+      await this.initModel();
+    }
+
+    try {
+      // The raw body carries no arguments, so the model id goes in a header
+      const headers: Record<string, string> = modelId ? { 'model-id': modelId } : {};
+      const results = await invoke<RecognitionResult[]>('recognize_image_bytes', imageBytes, { headers });
+      return results;
+    } catch (error) {
+      console.error('Recognition failed:', error);
+      throw error;
+    }
+  }
+
+  /**
+   * List past recognitions, newest first
+   * @param offset Number of entries to skip
+   * @param limit Page size
+   */
+  public async listHistory(offset = 0, limit?: number): Promise<HistoryPage> {
+    return invoke<HistoryPage>('list_history', { offset, limit });
+  }
+
+  /**
+   * Find past recognitions with a result label containing the given text
+   * @param label Text to search for, case-insensitive
+   */
+  public async searchHistory(label: string, offset = 0, limit?: number): Promise<HistoryPage> {
+    return invoke<HistoryPage>('search_history', { label, offset, limit });
+  }
+
+  /**
+   * Delete a single history entry
+   * @returns Whether an entry was removed
+   */
+  public async deleteHistoryEntry(id: number): Promise<boolean> {
+    return invoke<boolean>('delete_history_entry', { id });
+  }
+
+  /**
+   * Delete the whole recognition history
+   * @returns Number of entries removed
+   */
+  public async clearHistory(): Promise<number> {
+    return invoke<number>('clear_history');
+  }
+
+  /**
+   * Export stored results to a file chosen in a save dialog
+   * @param format CSV, JSON Lines or COCO-style JSON
+   * @param label Only export entries with a matching result label
+   * @returns The written path, or null if the dialog was cancelled
+   */
+  public async exportHistory(format: ExportFormat, label?: string): Promise<string | null> {
+    return invoke<string | null>('export_history', { format, label });
+  }
+
+  /**
+   * Write predicted labels as XMP keywords for images and folders.
+   * Runs as a dry run unless `dry_run: false` is passed; rewriting an existing
+   * image or sidecar additionally needs `confirm_overwrite: true`.
+   * Images are recognized with `modelId`, by default the default model.
+   */
+  public async tagImages(paths: string[], options?: TagOptions, modelId?: string): Promise<TagReport[]> {
+    if (!this.modelInitialized) {
+      await this.initModel();
+    }
+
+    return invoke<TagReport[]>('tag_images', { paths, options, modelId });
+  }
+
+  /**
+   * Plan sorting a directory's images into folders by top label or category.
+   * Nothing is moved until the returned `plan_id` is passed to `applyOrganize`.
+   * Images are recognized with `modelId`, by default the default model.
+   */
+  public async organizeDirectory(
+    directory: string,
+    options?: OrganizeOptions,
+    modelId?: string,
+  ): Promise<OrganizeResult> {
+    if (!this.modelInitialized) {
+      await this.initModel();
+    }
+
+    return invoke<OrganizeResult>('organize_directory', { directory, options, modelId });
+  }
+
+  /**
+   * Carry out a plan from `organizeDirectory` exactly as previewed
+   */
+  public async applyOrganize(planId: string): Promise<AppliedOrganize> {
+    return invoke<AppliedOrganize>('apply_organize', { planId });
+  }
+
+  /**
+   * Revert an organize run using the journal id it returned
+   */
+  public async undoOrganize(journalId: string): Promise<UndoReport> {
+    return invoke<UndoReport>('undo_organize', { journalId });
+  }
+
+  /**
+   * Models registered in the backend, sorted by id
+   */
+  public async listModels(): Promise<ModelInfo[]> {
+    return invoke<ModelInfo[]>('list_models');
+  }
+
+  /**
+   * Make a registered model, e.g. an imported one or an ensemble, the one
+   * recognition, tagging and organizing use
+   */
+  public async setDefaultModel(modelId: string): Promise<ModelInfo> {
+    return invoke<ModelInfo>('set_default_model', { modelId });
+  }
+
+  /**
+   * Combine registered models into an ensemble, usable wherever a model id is.
+   * The ensemble is saved and restored on the next start.
+   */
+  public async createEnsemble(config: EnsembleConfig): Promise<ModelInfo> {
+    return invoke<ModelInfo>('create_ensemble', { config });
+  }
+
+  /**
+   * Unregister and forget an ensemble; resolves to whether there was one
+   */
+  public async removeEnsemble(modelId: string): Promise<boolean> {
+    return invoke<boolean>('remove_ensemble', { modelId });
+  }
+
+  /**
+   * Pick a model file and its labels file, or a model package, and add them
+   * to the app's models.
+   * Resolves to null if a dialog was cancelled.
+   */
+  public async importModel(modelId?: string): Promise<ModelInfo | null> {
+    return invoke<ModelInfo | null>('import_model', { modelId });
+  }
+
+  /**
+   * Describe a model file, or the file of a registered model (by default the
+   * active one), including operators tract cannot run and why loading fails.
+   * An ensemble gets a report per member, in member order; anything else one report.
+   */
+  public async inspectModel(options?: { path?: string; modelId?: string }): Promise<InspectionReport[]> {
+    return invoke<InspectionReport[]>('inspect_model', { path: options?.path, modelId: options?.modelId });
+  }
+
+  /**
+   * Run two registered models on the same images or folders and report
+   * their disagreements, agreement rate and latency difference.
+   * Pass `saveReport` to also save the report as JSON, asking where with a save dialog.
+   */
+  public async compareModels(
+    modelA: string,
+    modelB: string,
+    paths: string[],
+    options?: CompareOptions,
+    saveReport?: boolean,
+  ): Promise<AbReport> {
+    return invoke<AbReport>('compare_models', { modelA, modelB, paths, options, saveReport });
+  }
+
+  /**
+   * Reload a model whenever its files change on disk, by default the active
+   * model. An ensemble is watched through its members, and follows them as
+   * they reload. Replaces any previous watch; resolves to the watched model id.
+   */
+  public async watchModel(modelId?: string): Promise<string> {
+    return invoke<string>('watch_model', { modelId });
+  }
+
+  /**
+   * Stop hot reloading; resolves to whether a model was being watched
+   */
+  public async unwatchModel(): Promise<boolean> {
+    return invoke<boolean>('unwatch_model');
+  }
+
+  /**
+   * Called after a watched model was rebuilt and swapped in
+   */
+  public onModelReloaded(callback: (model: ModelInfo) => void): Promise<UnlistenFn> {
+    return listen<ModelInfo>('model-reloaded', (event) => callback(event.payload));
+  }
+
+  /**
+   * Called when rebuilding a watched model failed; the previous model stays active
+   */
+  public onModelReloadFailed(callback: (failure: ModelReloadFailure) => void): Promise<UnlistenFn> {
+    return listen<ModelReloadFailure>('model-reload-failed', (event) => callback(event.payload));
+  }
+
+  /**
+   * Hit and miss counts of the recognition result cache
+   */
+  public async getCacheStats(): Promise<CacheStats> {
+    return invoke<CacheStats>('get_cache_stats');
+  }
 }
 
 export default RecognitionService.getInstance();
\ No newline at end of file